@@ -0,0 +1,149 @@
+use crate::config::{
+    get_config_key, ConfigPaths, VALID_DIFF_TOKEN_BUDGET, VALID_SUMMARY_CONCURRENCY,
+};
+use crate::error::{Error, Result};
+use crate::llm::Message;
+
+// A few bullet points per chunk, well above the cap on the commit message itself
+pub const SUMMARY_MAX_OUTPUT_TOKENS: u32 = 400;
+
+pub const SUMMARY_PROMPT: &str = "You summarize one part of a large git diff so that a commit message can later be written from all the summaries. Reply with a few short bullet points naming the changed files and describing what changed and why. Do not write a commit message.";
+
+pub fn diff_token_budget(config: &ConfigPaths) -> Result<usize> {
+    config_usize(config, VALID_DIFF_TOKEN_BUDGET)
+}
+
+// How many chunk summaries may be in flight at once
+pub fn summary_concurrency(config: &ConfigPaths) -> Result<usize> {
+    Ok(config_usize(config, VALID_SUMMARY_CONCURRENCY)?.max(1))
+}
+
+fn config_usize(config: &ConfigPaths, key: &str) -> Result<usize> {
+    let values = get_config_key(&[key], config)?;
+    values[0]
+        .parse::<usize>()
+        .map_err(|_| Error::invalid_value(key, format!("expected an integer, got {}", values[0])))
+}
+
+// Rough local estimate of roughly four characters per token, close enough for the BPE
+// tokenizers the providers use and stable across runs so chunk sizes are predictable
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+// Splits a unified diff into chunks that each fit `max_tokens`: whole files where possible,
+// then single hunks with their file header, then runs of lines for hunks that are still too big
+pub fn split_diff(diff: &str, max_tokens: usize) -> Vec<String> {
+    let max_tokens = max_tokens.max(1);
+    let mut pieces = Vec::new();
+
+    for file in split_before(diff, "diff --git ") {
+        if estimate_tokens(file) <= max_tokens {
+            pieces.push(file.to_string());
+            continue;
+        }
+
+        let sections = split_before(file, "@@");
+        let (header, hunks) = match sections.split_first() {
+            Some((first, rest)) if !first.starts_with("@@") && !rest.is_empty() => (*first, rest),
+            _ => ("", sections.as_slice()),
+        };
+        for hunk in hunks {
+            let piece = format!("{}{}", header, hunk);
+            if estimate_tokens(&piece) <= max_tokens {
+                pieces.push(piece);
+            } else {
+                pieces.extend(split_lines(header, hunk, max_tokens));
+            }
+        }
+    }
+
+    pack(pieces, max_tokens)
+}
+
+pub fn summary_messages(chunk: &str, part: usize, parts: usize) -> Vec<Message> {
+    vec![
+        Message::system(SUMMARY_PROMPT),
+        Message::user(&format!(
+            "Part {} of {} of the git diff:\n{}",
+            part, parts, chunk
+        )),
+    ]
+}
+
+// Stands in for the diff in the final request
+pub fn join_summaries(summaries: &[String]) -> String {
+    format!(
+        "The diff was too large to send in full, these are summaries of its {} parts:\n\n{}",
+        summaries.len(),
+        summaries.join("\n\n")
+    )
+}
+
+// Cuts `text` in front of every line starting with `marker`, keeping any text before the first one
+fn split_before<'a>(text: &'a str, marker: &str) -> Vec<&'a str> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        if line.starts_with(marker) && offset > start {
+            sections.push(&text[start..offset]);
+            start = offset;
+        }
+        offset += line.len();
+    }
+    if start < text.len() {
+        sections.push(&text[start..]);
+    }
+    sections
+}
+
+fn split_lines(header: &str, hunk: &str, max_tokens: usize) -> Vec<String> {
+    let budget = max_tokens.saturating_sub(estimate_tokens(header)).max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for line in hunk.split_inclusive('\n') {
+        // a single line over budget (minified files) is cut into character windows
+        for segment in split_chars(line, budget * 4) {
+            if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(segment) > budget
+            {
+                chunks.push(format!("{}{}", header, current));
+                current.clear();
+            }
+            current.push_str(segment);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(format!("{}{}", header, current));
+    }
+    chunks
+}
+
+fn split_chars(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (count, (index, _)) in text.char_indices().enumerate() {
+        if count > 0 && count % max_chars == 0 {
+            segments.push(&text[start..index]);
+            start = index;
+        }
+    }
+    segments.push(&text[start..]);
+    segments
+}
+
+// Greedily merges consecutive pieces while they stay within the budget
+fn pack(pieces: Vec<String>, max_tokens: usize) -> Vec<String> {
+    let mut chunks: Vec<String> = Vec::new();
+    for piece in pieces {
+        match chunks.last_mut() {
+            Some(last) if estimate_tokens(last) + estimate_tokens(&piece) <= max_tokens => {
+                last.push_str(&piece);
+            }
+            _ => chunks.push(piece),
+        }
+    }
+    chunks
+}
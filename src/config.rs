@@ -1,15 +1,154 @@
 use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
-use std::io::Error;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use toml;
 
+use crate::error::{Error, Result};
+use crate::redact::Redactor;
+
 #[derive(Deserialize, Serialize)]
 struct Config {
     openai_api_key: String,
     openai_url: String,
     openai_model: String,
     user_language: String,
+    #[serde(default = "default_provider")]
+    provider: String,
+    #[serde(default)]
+    anthropic_api_key: String,
+    #[serde(default = "default_anthropic_url")]
+    anthropic_url: String,
+    #[serde(default = "default_anthropic_model")]
+    anthropic_model: String,
+    #[serde(default = "default_ollama_url")]
+    ollama_url: String,
+    #[serde(default = "default_ollama_model")]
+    ollama_model: String,
+    #[serde(default = "default_ollama_api")]
+    ollama_api: String,
+    #[serde(default)]
+    azure_api_key: String,
+    #[serde(default)]
+    azure_endpoint: String,
+    #[serde(default)]
+    azure_deployment: String,
+    #[serde(default = "default_azure_api_version")]
+    azure_api_version: String,
+    #[serde(default)]
+    gemini_api_key: String,
+    #[serde(default = "default_gemini_url")]
+    gemini_url: String,
+    #[serde(default = "default_gemini_model")]
+    gemini_model: String,
+    #[serde(default = "default_stream")]
+    stream: String,
+    #[serde(default = "default_retry_max_attempts")]
+    retry_max_attempts: String,
+    #[serde(default = "default_retry_backoff_ms")]
+    retry_backoff_ms: String,
+    #[serde(default = "default_request_timeout_secs")]
+    request_timeout_secs: String,
+    #[serde(default = "default_connect_timeout_secs")]
+    connect_timeout_secs: String,
+    #[serde(default)]
+    fallback_providers: String,
+    #[serde(default = "default_diff_token_budget")]
+    diff_token_budget: String,
+    #[serde(default = "default_summary_concurrency")]
+    summary_concurrency: String,
+    #[serde(default)]
+    exclude_patterns: String,
+    #[serde(default = "default_rename_threshold")]
+    rename_threshold: String,
+    #[serde(default)]
+    redact_patterns: String,
+    #[serde(default = "default_secret_action")]
+    secret_action: String,
+    #[serde(default)]
+    commit_types: String,
+    #[serde(default)]
+    commit_scopes: String,
+    #[serde(default)]
+    prompt_template: String,
+    #[serde(default)]
+    api_key_command: String,
+    #[serde(default)]
+    api_key_file: String,
+}
+
+fn default_provider() -> String {
+    "openai".to_string()
+}
+
+fn default_anthropic_url() -> String {
+    "https://api.anthropic.com".to_string()
+}
+
+fn default_anthropic_model() -> String {
+    "claude-3-5-haiku-latest".to_string()
+}
+
+fn default_ollama_url() -> String {
+    "http://localhost:11434".to_string()
+}
+
+fn default_ollama_model() -> String {
+    "llama3.2".to_string()
+}
+
+fn default_ollama_api() -> String {
+    "chat".to_string()
+}
+
+fn default_azure_api_version() -> String {
+    "2024-06-01".to_string()
+}
+
+fn default_gemini_url() -> String {
+    "https://generativelanguage.googleapis.com".to_string()
+}
+
+fn default_gemini_model() -> String {
+    "gemini-1.5-flash".to_string()
+}
+
+fn default_stream() -> String {
+    "true".to_string()
+}
+
+fn default_retry_max_attempts() -> String {
+    "3".to_string()
+}
+
+fn default_retry_backoff_ms() -> String {
+    "500".to_string()
+}
+
+fn default_request_timeout_secs() -> String {
+    "60".to_string()
+}
+
+fn default_connect_timeout_secs() -> String {
+    "10".to_string()
+}
+
+fn default_diff_token_budget() -> String {
+    "12000".to_string()
+}
+
+fn default_summary_concurrency() -> String {
+    "4".to_string()
+}
+
+fn default_rename_threshold() -> String {
+    "50".to_string()
+}
+
+fn default_secret_action() -> String {
+    "redact".to_string()
 }
 
 #[derive(Deserialize, Serialize)]
@@ -24,6 +163,124 @@ pub const VALID_OPENAI_API_KEY: &str = "openai_api_key";
 pub const VALID_OPENAI_URL: &str = "openai_url";
 pub const VALID_OPENAI_MODEL: &str = "openai_model";
 pub const VALID_USER_LANGUAGE: &str = "user_language";
+pub const VALID_PROVIDER: &str = "provider";
+pub const VALID_ANTHROPIC_API_KEY: &str = "anthropic_api_key";
+pub const VALID_ANTHROPIC_URL: &str = "anthropic_url";
+pub const VALID_ANTHROPIC_MODEL: &str = "anthropic_model";
+pub const VALID_OLLAMA_URL: &str = "ollama_url";
+pub const VALID_OLLAMA_MODEL: &str = "ollama_model";
+pub const VALID_OLLAMA_API: &str = "ollama_api";
+pub const VALID_AZURE_API_KEY: &str = "azure_api_key";
+pub const VALID_AZURE_ENDPOINT: &str = "azure_endpoint";
+pub const VALID_AZURE_DEPLOYMENT: &str = "azure_deployment";
+pub const VALID_AZURE_API_VERSION: &str = "azure_api_version";
+pub const VALID_GEMINI_API_KEY: &str = "gemini_api_key";
+pub const VALID_GEMINI_URL: &str = "gemini_url";
+pub const VALID_GEMINI_MODEL: &str = "gemini_model";
+pub const VALID_STREAM: &str = "stream";
+pub const VALID_RETRY_MAX_ATTEMPTS: &str = "retry_max_attempts";
+pub const VALID_RETRY_BACKOFF_MS: &str = "retry_backoff_ms";
+pub const VALID_REQUEST_TIMEOUT_SECS: &str = "request_timeout_secs";
+pub const VALID_CONNECT_TIMEOUT_SECS: &str = "connect_timeout_secs";
+pub const VALID_FALLBACK_PROVIDERS: &str = "fallback_providers";
+pub const VALID_DIFF_TOKEN_BUDGET: &str = "diff_token_budget";
+pub const VALID_SUMMARY_CONCURRENCY: &str = "summary_concurrency";
+pub const VALID_EXCLUDE_PATTERNS: &str = "exclude_patterns";
+pub const VALID_RENAME_THRESHOLD: &str = "rename_threshold";
+pub const VALID_REDACT_PATTERNS: &str = "redact_patterns";
+pub const VALID_SECRET_ACTION: &str = "secret_action";
+pub const VALID_COMMIT_TYPES: &str = "commit_types";
+pub const VALID_COMMIT_SCOPES: &str = "commit_scopes";
+pub const VALID_PROMPT_TEMPLATE: &str = "prompt_template";
+pub const VALID_API_KEY_COMMAND: &str = "api_key_command";
+pub const VALID_API_KEY_FILE: &str = "api_key_file";
+
+pub const CONFIG_KEYS: &[&str] = &[
+    VALID_PROVIDER,
+    VALID_FALLBACK_PROVIDERS,
+    VALID_OPENAI_API_KEY,
+    VALID_OPENAI_URL,
+    VALID_OPENAI_MODEL,
+    VALID_ANTHROPIC_API_KEY,
+    VALID_ANTHROPIC_URL,
+    VALID_ANTHROPIC_MODEL,
+    VALID_OLLAMA_URL,
+    VALID_OLLAMA_MODEL,
+    VALID_OLLAMA_API,
+    VALID_AZURE_API_KEY,
+    VALID_AZURE_ENDPOINT,
+    VALID_AZURE_DEPLOYMENT,
+    VALID_AZURE_API_VERSION,
+    VALID_GEMINI_API_KEY,
+    VALID_GEMINI_URL,
+    VALID_GEMINI_MODEL,
+    VALID_API_KEY_COMMAND,
+    VALID_API_KEY_FILE,
+    VALID_STREAM,
+    VALID_RETRY_MAX_ATTEMPTS,
+    VALID_RETRY_BACKOFF_MS,
+    VALID_REQUEST_TIMEOUT_SECS,
+    VALID_CONNECT_TIMEOUT_SECS,
+    VALID_DIFF_TOKEN_BUDGET,
+    VALID_SUMMARY_CONCURRENCY,
+    VALID_EXCLUDE_PATTERNS,
+    VALID_RENAME_THRESHOLD,
+    VALID_REDACT_PATTERNS,
+    VALID_SECRET_ACTION,
+    VALID_COMMIT_TYPES,
+    VALID_COMMIT_SCOPES,
+    VALID_PROMPT_TEMPLATE,
+    VALID_USER_LANGUAGE,
+];
+
+// Keys that hold credentials or say how to fetch them, the files setting them are kept private
+pub const SECRET_KEYS: &[&str] = &[
+    VALID_OPENAI_API_KEY,
+    VALID_ANTHROPIC_API_KEY,
+    VALID_AZURE_API_KEY,
+    VALID_GEMINI_API_KEY,
+    VALID_API_KEY_COMMAND,
+    VALID_API_KEY_FILE,
+];
+
+// The committed, team wide config in the repository root
+pub const REPO_CONFIG_FILE: &str = ".commit_crafter.toml";
+// The only keys a repository may set: its conventions, never where requests go, which
+// credentials they carry or which command fetches them, whatever a cloned repository contains
+pub const REPO_KEYS: &[&str] = &[
+    VALID_USER_LANGUAGE,
+    VALID_COMMIT_TYPES,
+    VALID_COMMIT_SCOPES,
+    VALID_PROMPT_TEMPLATE,
+    VALID_EXCLUDE_PATTERNS,
+    VALID_REDACT_PATTERNS,
+];
+// COMMIT_CRAFTER_OPENAI_MODEL overrides openai_model and so on
+pub const ENV_PREFIX: &str = "COMMIT_CRAFTER_";
+// Sets the model of whichever provider is in use
+pub const ENV_MODEL: &str = "COMMIT_CRAFTER_MODEL";
+
+// The key holding the model of each provider, azure names a deployment instead
+const MODEL_KEYS: &[(&str, &str)] = &[
+    ("openai", VALID_OPENAI_MODEL),
+    ("anthropic", VALID_ANTHROPIC_MODEL),
+    ("ollama", VALID_OLLAMA_MODEL),
+    ("azure", VALID_AZURE_DEPLOYMENT),
+    ("gemini", VALID_GEMINI_MODEL),
+];
+
+// Variables other tools already read the same credentials from, below the COMMIT_CRAFTER_ ones
+const VENDOR_ENV_VARS: &[(&str, &str)] = &[
+    (VALID_OPENAI_API_KEY, "OPENAI_API_KEY"),
+    (VALID_ANTHROPIC_API_KEY, "ANTHROPIC_API_KEY"),
+    (VALID_AZURE_API_KEY, "AZURE_OPENAI_API_KEY"),
+    (VALID_GEMINI_API_KEY, "GEMINI_API_KEY"),
+];
+const CONFIG_FILE: &str = "config.toml";
+
+pub const PROVIDERS: &[&str] = &["openai", "anthropic", "ollama", "azure", "gemini"];
+pub const OLLAMA_APIS: &[&str] = &["chat", "generate"];
+pub const SECRET_ACTIONS: &[&str] = &["redact", "abort"];
 
 const PROMPT_ZH: &str = "请根据以下git diff内容，结合最近5条提交信息，生成一条风格一致、语言纯正的简洁Git提交信息。请仅用简体中文描述，避免中英混用。根据本次更改的性质，从以下前缀中选择一个开头：'build'（构建系统）、'chore'（杂务）、'ci'（持续集成）、'docs'（文档）、'feat'（新功能）、'fix'（修复）、'perf'（性能）、'refactor'（重构）、'style'（样式）、'test'（测试）。提交信息应重点突出本次更改的核心目的和影响，避免冗长和无关细节。示例：\n\nfeat: 支持多语言提交信息生成\nfix: 修复API密钥配置无法保存的问题\n\n请生成一条与上文风格一致的提交信息：";
 
@@ -33,97 +290,513 @@ const PROMPT_JP: &str = "以下のgitの差分内容と直近5件のコミット
 
 const PROMPT_ZH_TW: &str = "請根據以下git差異內容，以及最近5筆提交訊息，生成一條風格一致、語言純正且精簡的Git提交訊息。請只用繁體中文描述，避免中英混合。根據此次更改的性質，從下列前綴選擇一個作為開頭：'build'（構建系統）、'chore'（雜務）、'ci'（持續整合）、'docs'（文件）、'feat'（新功能）、'fix'（修復）、'perf'（效能）、'refactor'（重構）、'style'（樣式）、'test'（測試）。訊息需明確說明此更動的主要目的與影響，避免冗長或無關資訊。範例：\n\nfeat: 支援多語言提交訊息生成\nfix: 修正API金鑰設定無法儲存的問題\n\n請產生一條符合上述風格的提交訊息：";
 
-pub fn validate_config_key(key: &str) -> Result<&str, &'static str> {
+pub fn validate_config_key(key: &str) -> Result<&str> {
     match key {
         VALID_OPENAI_API_KEY | VALID_OPENAI_URL | VALID_OPENAI_MODEL => Ok(key),
-        VALID_USER_LANGUAGE => Ok(key),
-        _ => Err("Invalid configuration key"),
+        VALID_ANTHROPIC_API_KEY | VALID_ANTHROPIC_URL | VALID_ANTHROPIC_MODEL => Ok(key),
+        VALID_OLLAMA_URL | VALID_OLLAMA_MODEL | VALID_OLLAMA_API => Ok(key),
+        VALID_AZURE_API_KEY | VALID_AZURE_ENDPOINT => Ok(key),
+        VALID_AZURE_DEPLOYMENT | VALID_AZURE_API_VERSION => Ok(key),
+        VALID_GEMINI_API_KEY | VALID_GEMINI_URL | VALID_GEMINI_MODEL => Ok(key),
+        VALID_RETRY_MAX_ATTEMPTS | VALID_RETRY_BACKOFF_MS => Ok(key),
+        VALID_REQUEST_TIMEOUT_SECS | VALID_CONNECT_TIMEOUT_SECS => Ok(key),
+        VALID_DIFF_TOKEN_BUDGET
+        | VALID_SUMMARY_CONCURRENCY
+        | VALID_EXCLUDE_PATTERNS
+        | VALID_RENAME_THRESHOLD
+        | VALID_REDACT_PATTERNS
+        | VALID_SECRET_ACTION => Ok(key),
+        VALID_COMMIT_TYPES | VALID_COMMIT_SCOPES | VALID_PROMPT_TEMPLATE => Ok(key),
+        VALID_API_KEY_COMMAND | VALID_API_KEY_FILE => Ok(key),
+        VALID_USER_LANGUAGE | VALID_PROVIDER | VALID_FALLBACK_PROVIDERS | VALID_STREAM => Ok(key),
+        _ => Err(Error::InvalidKey(key.to_string())),
     }
 }
 
-pub fn get_config_key<P: AsRef<Path>>(keys: &[&str], path: P) -> Result<Vec<String>, &'static str> {
-    let config_file = fs::read_to_string(path.as_ref()).expect("Could not read config file");
-    let config: Config = toml::from_str(&config_file).expect("Could not parse config file");
+// Where a resolved value came from, lowest precedence first; Env names the variable
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigSource {
+    Default,
+    Global,
+    Project,
+    Repo,
+    Env(String),
+    Cli,
+}
 
-    let mut result = Vec::new();
+impl ConfigSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigSource::Default => "default",
+            ConfigSource::Global => "global",
+            ConfigSource::Project => "project",
+            ConfigSource::Repo => "repo",
+            ConfigSource::Env(_) => "env",
+            ConfigSource::Cli => "cli",
+        }
+    }
+}
 
-    for key in keys {
-        match validate_config_key(key) {
-            Ok(vaild_key) => {
-                let value = match vaild_key {
-                    VALID_OPENAI_API_KEY => config.openai_api_key.clone(),
-                    VALID_OPENAI_URL => config.openai_url.clone(),
-                    VALID_OPENAI_MODEL => config.openai_model.clone(),
-                    VALID_USER_LANGUAGE => config.user_language.clone(),
-                    _ => panic!("Invalid configuration key"),
-                };
-                result.push(value);
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Env(var) => write!(f, "env {}", var),
+            source => write!(f, "{}", source.as_str()),
+        }
+    }
+}
+
+// `key=value` pairs from the -c flag, validated; they override every other layer once passed
+// to `ConfigPaths::overrides`
+pub fn parse_cli_overrides<S: AsRef<str>>(overrides: &[S]) -> Result<Vec<(String, String)>> {
+    let mut parsed = Vec::new();
+    for entry in overrides {
+        let entry = entry.as_ref();
+        let (key, value) = entry.split_once('=').ok_or_else(|| {
+            Error::invalid_value("--config", format!("expected key=value, got {}", entry))
+        })?;
+        let key = validate_config_key(key.trim())?;
+        validate_config_value(key, value)?;
+        parsed.push((key.to_string(), value.to_string()));
+    }
+    Ok(parsed)
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    let config_file = fs::read_to_string(path).map_err(|e| Error::config_io(path, e))?;
+    config_file
+        .parse::<toml::Table>()
+        .map_err(|e| Error::config_parse(path, e))
+}
+
+// Where the layers of a config come from. `dir` holds the config.toml that is written to and
+// prompt.toml; the global config sits below it and the repository's committed
+// .commit_crafter.toml above it, the -c overrides above everything
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigPaths {
+    pub dir: PathBuf,
+    pub global: Option<PathBuf>,
+    pub repo: Option<PathBuf>,
+    pub overrides: Vec<(String, String)>,
+}
+
+impl ConfigPaths {
+    // A config directory read on its own
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        ConfigPaths {
+            dir: dir.as_ref().to_path_buf(),
+            global: None,
+            repo: None,
+            overrides: Vec::new(),
+        }
+    }
+
+    pub fn global<P: AsRef<Path>>(mut self, file: P) -> Self {
+        self.global = Some(file.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn repo<P: AsRef<Path>>(mut self, file: P) -> Self {
+        self.repo = Some(file.as_ref().to_path_buf());
+        self
+    }
+
+    // As returned by `parse_cli_overrides`
+    pub fn overrides(mut self, overrides: Vec<(String, String)>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.dir.join(CONFIG_FILE)
+    }
+
+    // The files, lowest precedence first. Outside a repository `dir` is the global directory
+    // itself, its config.toml then reports as the global layer
+    pub fn files(&self) -> Vec<(ConfigSource, PathBuf)> {
+        let config_file = self.config_file();
+        let mut files = Vec::new();
+        match &self.global {
+            Some(global) if *global == config_file => {
+                files.push((ConfigSource::Global, config_file))
             }
-            Err(_) => return Err("Invalid configuration key"),
+            Some(global) => {
+                files.push((ConfigSource::Global, global.clone()));
+                files.push((ConfigSource::Project, config_file));
+            }
+            None => files.push((ConfigSource::Project, config_file)),
+        }
+        if let Some(repo) = &self.repo {
+            files.push((ConfigSource::Repo, repo.clone()));
         }
+        files
+    }
+}
+
+// What `get_config_key` reads: a single config file on its own, or every layer of a
+// ConfigPaths
+pub trait ConfigLayers {
+    // The files, lowest precedence first
+    fn files(&self) -> Vec<(ConfigSource, PathBuf)>;
+    // The one file that has to exist, the others are optional
+    fn config_file(&self) -> PathBuf;
+    fn overrides(&self) -> &[(String, String)] {
+        &[]
+    }
+}
+
+impl<P: AsRef<Path>> ConfigLayers for P {
+    fn files(&self) -> Vec<(ConfigSource, PathBuf)> {
+        vec![(ConfigSource::Project, self.as_ref().to_path_buf())]
+    }
+
+    fn config_file(&self) -> PathBuf {
+        self.as_ref().to_path_buf()
     }
-    Ok(result)
 }
 
-pub fn set_config_key<P: AsRef<Path> + Clone>(
-    key: &str,
-    value: &str,
-    path: P,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let config_file = fs::read_to_string(path.as_ref()).expect("Could not read config file");
-    let mut config: Config = toml::from_str(&config_file).expect("Could not parse config file");
-
-    let key = match validate_config_key(key) {
-        Ok(valid_key) => valid_key,
-        Err(e) => {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                e,
-            )))
+impl ConfigLayers for ConfigPaths {
+    fn files(&self) -> Vec<(ConfigSource, PathBuf)> {
+        ConfigPaths::files(self)
+    }
+
+    fn config_file(&self) -> PathBuf {
+        ConfigPaths::config_file(self)
+    }
+
+    fn overrides(&self) -> &[(String, String)] {
+        &self.overrides
+    }
+}
+
+impl ConfigLayers for &ConfigPaths {
+    fn files(&self) -> Vec<(ConfigSource, PathBuf)> {
+        ConfigPaths::files(self)
+    }
+
+    fn config_file(&self) -> PathBuf {
+        ConfigPaths::config_file(self)
+    }
+
+    fn overrides(&self) -> &[(String, String)] {
+        &self.overrides
+    }
+}
+
+fn layers<C: ConfigLayers + ?Sized>(config: &C) -> Result<Vec<(ConfigSource, toml::Table)>> {
+    let defaults = generate_config_toml()
+        .parse::<toml::Table>()
+        .expect("default config is valid toml");
+    let mut layers = vec![(ConfigSource::Default, defaults)];
+
+    let config_file = config.config_file();
+    for (source, file) in config.files() {
+        if file == config_file || file.exists() {
+            let table = read_table(&file)?;
+            if source == ConfigSource::Repo {
+                for key in table.keys() {
+                    if let Some(value) = table_value(&table, key) {
+                        check_repo_value(key, &value, &file)?;
+                    }
+                }
+            }
+            layers.push((source, table));
         }
-    };
+    }
 
-    match key {
-        VALID_OPENAI_API_KEY => config.openai_api_key = value.to_string(),
-        VALID_OPENAI_URL => config.openai_url = value.to_string(),
-        VALID_OPENAI_MODEL => config.openai_model = value.to_string(),
-        VALID_USER_LANGUAGE => config.user_language = value.to_string(),
-        _ => {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Invalid configuration key",
-            )))
+    let mut cli_layer = toml::Table::new();
+    for (key, value) in config.overrides() {
+        cli_layer.insert(key.clone(), toml::Value::String(value.clone()));
+    }
+
+    // COMMIT_CRAFTER_MODEL follows the provider in effect, which env and -c can change as well
+    let provider = table_value(&cli_layer, VALID_PROVIDER)
+        .or_else(|| env_override(VALID_PROVIDER, "").map(|(_, value)| value))
+        .or_else(|| {
+            layers
+                .iter()
+                .rev()
+                .find_map(|(_, table)| table_value(table, VALID_PROVIDER))
+        })
+        .unwrap_or_default();
+
+    // one layer per variable so each value can report where it came from
+    for key in CONFIG_KEYS {
+        if let Some((var, value)) = env_override(key, &provider) {
+            validate_config_value(key, &value).map_err(|e| match e {
+                Error::InvalidValue { message, .. } => Error::invalid_value(&var, message),
+                e => e,
+            })?;
+            let mut env_layer = toml::Table::new();
+            env_layer.insert(key.to_string(), toml::Value::String(value));
+            layers.push((ConfigSource::Env(var), env_layer));
+        }
+    }
+
+    layers.push((ConfigSource::Cli, cli_layer));
+    Ok(layers)
+}
+
+// The variables that can set `key`, most specific first
+pub fn env_vars(key: &str, provider: &str) -> Vec<String> {
+    let mut vars = vec![format!("{}{}", ENV_PREFIX, key.to_uppercase())];
+    if MODEL_KEYS.contains(&(provider, key)) {
+        vars.push(ENV_MODEL.to_string());
+    }
+    for (vendor_key, var) in VENDOR_ENV_VARS {
+        if *vendor_key == key {
+            vars.push(var.to_string());
         }
     }
-    let new_config = toml::to_string(&config).expect("Could not serialize config");
-    fs::write(path, new_config).expect("Could not write to config file");
+    vars
+}
+
+// The first of `env_vars` that is set to something, with its name
+fn env_override(key: &str, provider: &str) -> Option<(String, String)> {
+    env_vars(key, provider)
+        .into_iter()
+        .find_map(|var| match env::var(&var) {
+            Ok(value) if !value.is_empty() => Some((var, value)),
+            _ => None,
+        })
+}
+
+// The file behind the `source` layer of `config`, None for the layers that are not files
+pub fn layer_file<C: ConfigLayers>(config: C, source: &ConfigSource) -> Option<PathBuf> {
+    config
+        .files()
+        .into_iter()
+        .find(|(file_source, _)| file_source == source)
+        .map(|(_, file)| file)
+}
+
+// An empty string counts as unset so a blank key does not hide the layer below it
+fn table_value(table: &toml::Table, key: &str) -> Option<String> {
+    match table.get(key)? {
+        toml::Value::String(value) if value.is_empty() => None,
+        toml::Value::String(value) => Some(value.clone()),
+        value => Some(value.to_string()),
+    }
+}
+
+pub fn get_config_key_with_source<C: ConfigLayers>(
+    keys: &[&str],
+    config: C,
+) -> Result<Vec<(String, ConfigSource)>> {
+    let layers = layers(&config)?;
 
+    let mut result = Vec::new();
+    for key in keys {
+        let key = validate_config_key(key)?;
+        let value = layers
+            .iter()
+            .rev()
+            .find_map(|(source, table)| {
+                table_value(table, key).map(|value| (value, source.clone()))
+            })
+            .unwrap_or_else(|| (String::new(), ConfigSource::Default));
+        result.push(value);
+    }
+    Ok(result)
+}
+
+pub fn get_config_key<C: ConfigLayers>(keys: &[&str], config: C) -> Result<Vec<String>> {
+    let values = get_config_key_with_source(keys, config)?;
+    Ok(values.into_iter().map(|(value, _)| value).collect())
+}
+
+// Only the key being set is written, so the file keeps just what was chosen for its layer
+pub fn set_config_key<P: AsRef<Path> + Clone>(key: &str, value: &str, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let key = validate_config_key(key)?;
+    validate_config_value(key, value)?;
+    if path.file_name() == Some(OsStr::new(REPO_CONFIG_FILE)) {
+        check_repo_value(key, value, path)?;
+    }
+
+    let mut config = read_table(path)?;
+    config.insert(key.to_string(), toml::Value::String(value.to_string()));
+    let new_config = toml::to_string(&config).map_err(|e| Error::config_parse(path, e))?;
+    fs::write(path, new_config).map_err(|e| Error::config_io(path, e))?;
+
+    // once it holds a credential the file is for its owner only
+    if SECRET_KEYS.contains(&key) {
+        restrict_to_owner(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_to_owner(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .map_err(|e| Error::config_io(path, e))
+}
+
+#[cfg(not(unix))]
+fn restrict_to_owner(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+pub fn validate_config_value(key: &str, value: &str) -> Result<()> {
+    match validate_config_key(key)? {
+        VALID_PROVIDER if !PROVIDERS.contains(&value) => Err(Error::invalid_value(
+            key,
+            format!("expected one of: {}", PROVIDERS.join(", ")),
+        )),
+        VALID_FALLBACK_PROVIDERS => {
+            // comma separated `provider` or `provider:model` entries
+            for entry in value.split(',').filter(|entry| !entry.trim().is_empty()) {
+                let name = entry.split(':').next().unwrap_or_default().trim();
+                if !PROVIDERS.contains(&name) {
+                    return Err(Error::invalid_value(
+                        key,
+                        format!(
+                            "unknown provider {}, expected one of: {}",
+                            name,
+                            PROVIDERS.join(", ")
+                        ),
+                    ));
+                }
+            }
+            Ok(())
+        }
+        VALID_OLLAMA_API if !OLLAMA_APIS.contains(&value) => Err(Error::invalid_value(
+            key,
+            format!("expected one of: {}", OLLAMA_APIS.join(", ")),
+        )),
+        VALID_STREAM if value != "true" && value != "false" => {
+            Err(Error::invalid_value(key, "expected true or false"))
+        }
+        VALID_RETRY_MAX_ATTEMPTS
+        | VALID_REQUEST_TIMEOUT_SECS
+        | VALID_CONNECT_TIMEOUT_SECS
+        | VALID_SUMMARY_CONCURRENCY => validate_integer(key, value, 1),
+        VALID_RETRY_BACKOFF_MS => validate_integer(key, value, 0),
+        VALID_DIFF_TOKEN_BUDGET => validate_integer(key, value, 100),
+        // a percentage, 0 turns rename detection off
+        VALID_RENAME_THRESHOLD => match value.parse::<u32>() {
+            Ok(percent) if percent <= 100 => Ok(()),
+            _ => Err(Error::invalid_value(
+                key,
+                "expected a percentage from 0 to 100",
+            )),
+        },
+        VALID_REDACT_PATTERNS => {
+            for pattern in value.split_whitespace() {
+                if let Err(e) = regex::Regex::new(pattern) {
+                    return Err(Error::invalid_value(key, e));
+                }
+            }
+            Ok(())
+        }
+        VALID_SECRET_ACTION if !SECRET_ACTIONS.contains(&value) => Err(Error::invalid_value(
+            key,
+            format!("expected one of: {}", SECRET_ACTIONS.join(", ")),
+        )),
+        // comma separated single words, e.g. `feat,fix,docs`
+        VALID_COMMIT_TYPES | VALID_COMMIT_SCOPES => {
+            match split_list(value)
+                .iter()
+                .find(|entry| entry.contains(char::is_whitespace))
+            {
+                Some(entry) => Err(Error::invalid_value(
+                    key,
+                    format!("expected comma separated words, got {}", entry),
+                )),
+                None => Ok(()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+// The repository config is committed and shared, so credentials and anything the diff redaction
+// would mask are refused
+fn check_repo_value(key: &str, value: &str, path: &Path) -> Result<()> {
+    if !REPO_KEYS.contains(&key) {
+        return Err(Error::invalid_value(
+            key,
+            format!(
+                "cannot be set in the committed {}, it only accepts: {}",
+                path.display(),
+                REPO_KEYS.join(", ")
+            ),
+        ));
+    }
+
+    let redaction = Redactor::new::<&str>(&[])?.redact(value);
+    if redaction.total() > 0 {
+        return Err(Error::invalid_value(
+            key,
+            format!(
+                "looks like a secret ({}), it does not belong in the committed {}",
+                redaction.describe(),
+                path.display()
+            ),
+        ));
+    }
     Ok(())
 }
 
-pub fn get_language<P: AsRef<Path> + Clone>(user_language: &str, path: P) -> String {
-    let prompt_file = fs::read_to_string(path).expect("Could not read prompt config file");
+// How `config list` and `config get` show a value: secrets only as much as tells them apart,
+// e.g. sk-…abcd, and short ones not at all
+pub fn display_value(key: &str, value: &str) -> String {
+    if !SECRET_KEYS.contains(&key) || value.is_empty() {
+        return value.to_string();
+    }
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 12 {
+        return "…".to_string();
+    }
+    let head: String = chars[..3].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+pub fn split_list(value: &str) -> Vec<&str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+fn validate_integer(key: &str, value: &str, min: u64) -> Result<()> {
+    match value.parse::<u64>() {
+        Ok(number) if number >= min => Ok(()),
+        _ => Err(Error::invalid_value(
+            key,
+            format!("expected an integer >= {}", min),
+        )),
+    }
+}
+
+pub fn get_language<P: AsRef<Path> + Clone>(user_language: &str, path: P) -> Result<String> {
+    let path = path.as_ref();
+    let prompt_file = fs::read_to_string(path).map_err(|e| Error::config_io(path, e))?;
     let prompt_config: PromptConfig =
-        toml::from_str(&prompt_file).expect("Could not parse prompt config file");
+        toml::from_str(&prompt_file).map_err(|e| Error::config_parse(path, e))?;
 
     match user_language {
-        "zh" => prompt_config.prompt_zh.clone(),
-        "en" => prompt_config.prompt_en.clone(),
-        "jp" => prompt_config.prompt_jp.clone(),
-        "zh_tw" => prompt_config.prompt_zh_tw.clone(),
-        _ => panic!("Invalid user language"),
+        "zh" => Ok(prompt_config.prompt_zh),
+        "en" => Ok(prompt_config.prompt_en),
+        "jp" => Ok(prompt_config.prompt_jp),
+        "zh_tw" => Ok(prompt_config.prompt_zh_tw),
+        _ => Err(Error::InvalidLanguage(user_language.to_string())),
     }
 }
 
-pub fn move_prompt_toml<P: AsRef<Path> + Clone>(path: P) {
+pub fn move_prompt_toml<P: AsRef<Path> + Clone>(path: P) -> Result<()> {
+    let path = path.as_ref();
     let prompt_config = PromptConfig {
         prompt_zh: PROMPT_ZH.to_string(),
         prompt_en: PROMPT_EN.to_string(),
         prompt_jp: PROMPT_JP.to_string(),
         prompt_zh_tw: PROMPT_ZH_TW.to_string(),
     };
-    let prompt_toml = toml::to_string(&prompt_config).expect("Could not serialize prompt config");
-    fs::write(path, prompt_toml).expect("Could not write to prompt config file");
+    let prompt_toml = toml::to_string(&prompt_config).map_err(|e| Error::config_parse(path, e))?;
+    fs::write(path, prompt_toml).map_err(|e| Error::config_io(path, e))
 }
 
 pub fn generate_config_toml() -> String {
@@ -132,30 +805,59 @@ pub fn generate_config_toml() -> String {
         openai_url: "https://api.openai.com".to_string(),
         openai_model: "gpt-4o-mini".to_string(),
         user_language: "en".to_string(),
+        provider: default_provider(),
+        anthropic_api_key: "".to_string(),
+        anthropic_url: default_anthropic_url(),
+        anthropic_model: default_anthropic_model(),
+        ollama_url: default_ollama_url(),
+        ollama_model: default_ollama_model(),
+        ollama_api: default_ollama_api(),
+        azure_api_key: "".to_string(),
+        azure_endpoint: "".to_string(),
+        azure_deployment: "".to_string(),
+        azure_api_version: default_azure_api_version(),
+        gemini_api_key: "".to_string(),
+        gemini_url: default_gemini_url(),
+        gemini_model: default_gemini_model(),
+        stream: default_stream(),
+        retry_max_attempts: default_retry_max_attempts(),
+        retry_backoff_ms: default_retry_backoff_ms(),
+        request_timeout_secs: default_request_timeout_secs(),
+        connect_timeout_secs: default_connect_timeout_secs(),
+        fallback_providers: "".to_string(),
+        diff_token_budget: default_diff_token_budget(),
+        summary_concurrency: default_summary_concurrency(),
+        exclude_patterns: "".to_string(),
+        rename_threshold: default_rename_threshold(),
+        redact_patterns: "".to_string(),
+        secret_action: default_secret_action(),
+        commit_types: "".to_string(),
+        commit_scopes: "".to_string(),
+        prompt_template: "".to_string(),
+        api_key_command: "".to_string(),
+        api_key_file: "".to_string(),
     };
     toml::to_string(&config).expect("Could not serialize config")
 }
 
-pub fn write_config_to_toml(config_toml: &str, path: &Path) -> Result<(), Error> {
-    fs::write(path, config_toml).expect("Could not write to config file");
-    Ok(())
+pub fn write_config_to_toml(config_toml: &str, path: &Path) -> Result<()> {
+    fs::write(path, config_toml).map_err(|e| Error::config_io(path, e))
 }
 
-pub fn ensure_config_initialized<P: AsRef<Path>>(
-    config_dir: P,
-) -> Result<(), Box<dyn std::error::Error>> {
+pub fn ensure_config_initialized<P: AsRef<Path>>(config_dir: P) -> Result<()> {
     let config_dir_path = config_dir.as_ref();
     let config_file_path = config_dir_path.join("config.toml");
     let prompt_file_path = config_dir_path.join("prompt.toml");
 
     // Ensure the directory exists
-    fs::create_dir_all(config_dir_path)?;
+    fs::create_dir_all(config_dir_path).map_err(|e| Error::config_io(config_dir_path, e))?;
 
-    // Initialize config.toml if it doesn't exist
+    // Initialize config.toml if it doesn't exist, empty so the defaults and the global config
+    // show through until a key is set here. Notices go to stderr, the hook captures stdout as
+    // the commit message
     if !config_file_path.exists() {
-        let default_config = generate_config_toml();
-        fs::write(&config_file_path, default_config)?;
-        println!(
+        write_config_to_toml("", &config_file_path)?;
+        eprintln!(
             "Created new project config at: {}",
             config_file_path.display()
         );
@@ -163,8 +865,8 @@ pub fn ensure_config_initialized<P: AsRef<Path>>(
 
     // Initialize prompt.toml if it doesn't exist
     if !prompt_file_path.exists() {
-        move_prompt_toml(&prompt_file_path);
-        println!(
+        move_prompt_toml(&prompt_file_path)?;
+        eprintln!(
             "Created new prompt config at: {}",
             prompt_file_path.display()
         );
@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::chunking::{
+    diff_token_budget, estimate_tokens, join_summaries, split_diff, summary_concurrency,
+    summary_messages, SUMMARY_MAX_OUTPUT_TOKENS,
+};
+use crate::config::ConfigPaths;
+use crate::error::{Error, Result};
+use crate::llm::{self, GeneratedMessage, LlmProvider, Message, ProviderFailure, TokenUsage};
+use crate::redact::redact_diff;
+
+// Summaries of summaries are only needed for enormous diffs, stop in case they do not shrink
+const MAX_SUMMARY_ROUNDS: usize = 3;
+
+// Entry point for embedding commit_crafter: collects the inputs and returns the message
+// instead of printing it.
+//
+//     let message = CommitCrafter::with_paths(&project::config_paths()?)
+//         .diff(&diff)
+//         .history(&recent_commits)
+//         .generate()?;
+pub struct CommitCrafter {
+    config: ConfigPaths,
+    diff: String,
+    history: Vec<String>,
+    provider: Option<Arc<dyn LlmProvider>>,
+    diff_token_budget: Option<usize>,
+    summary_concurrency: Option<usize>,
+}
+
+// What `generate` would send, assembled without any network call
+#[derive(Debug)]
+pub struct DryRun {
+    pub provider: String,
+    pub model: String,
+    // The bodies in the order they would be posted: the final request, or for a diff over the
+    // token budget the chunk summary requests, since the final request is built from their
+    // replies
+    pub bodies: Vec<Value>,
+    // of the requests in `bodies`
+    pub estimated_tokens: usize,
+    // chunks the diff would be split into and summarized first, 0 when it fits the budget
+    pub summary_chunks: usize,
+    // secrets masked in the diff, by detector
+    pub redacted: BTreeMap<String, usize>,
+}
+
+// The diff as it goes into the final request, with what it took to get there
+struct PreparedDiff {
+    diff: String,
+    usage: Option<TokenUsage>,
+    failures: Vec<ProviderFailure>,
+    redacted: BTreeMap<String, usize>,
+}
+
+impl CommitCrafter {
+    // `config_dir` holds config.toml and prompt.toml, read on their own without the global or
+    // repository layers (see `with_paths`)
+    pub fn new(config_dir: &str) -> Self {
+        CommitCrafter::with_paths(&ConfigPaths::new(config_dir))
+    }
+
+    // Reads the config through all of `paths`, as `project::config_paths()` returns them for
+    // the current repository
+    pub fn with_paths(paths: &ConfigPaths) -> Self {
+        CommitCrafter {
+            config: paths.clone(),
+            diff: String::new(),
+            history: Vec::new(),
+            provider: None,
+            diff_token_budget: None,
+            summary_concurrency: None,
+        }
+    }
+
+    pub fn diff(mut self, diff: &str) -> Self {
+        self.diff = diff.to_string();
+        self
+    }
+
+    pub fn history(mut self, history: &[String]) -> Self {
+        self.history = history.to_vec();
+        self
+    }
+
+    // Use this provider instead of the configured provider and fallback chain
+    pub fn provider(mut self, provider: Box<dyn LlmProvider>) -> Self {
+        self.provider = Some(Arc::from(provider));
+        self
+    }
+
+    // Overrides the diff_token_budget config key
+    pub fn diff_token_budget(mut self, tokens: usize) -> Self {
+        self.diff_token_budget = Some(tokens);
+        self
+    }
+
+    // Overrides the summary_concurrency config key
+    pub fn summary_concurrency(mut self, limit: usize) -> Self {
+        self.summary_concurrency = Some(limit.max(1));
+        self
+    }
+
+    pub fn generate(&self) -> Result<GeneratedMessage> {
+        let start = Instant::now();
+        let prepared = self.prepare_diff()?;
+        let message = match &self.provider {
+            Some(provider) => llm::generate_with_provider(
+                provider.as_ref(),
+                &prepared.diff,
+                &self.history,
+                &self.config,
+            )?,
+            None => llm::generate_commit_message(&prepared.diff, &self.history, &self.config)?,
+        };
+        Ok(finish(message, prepared, start))
+    }
+
+    // Like `generate`, but hands every token of the final message to `on_token` as it arrives
+    pub fn generate_stream(&self, on_token: &mut dyn FnMut(&str)) -> Result<GeneratedMessage> {
+        let start = Instant::now();
+        let prepared = self.prepare_diff()?;
+        let message = match &self.provider {
+            Some(provider) => llm::stream_with_provider(
+                provider.as_ref(),
+                &prepared.diff,
+                &self.history,
+                &self.config,
+                on_token,
+            )?,
+            None => {
+                llm::stream_commit_message(&prepared.diff, &self.history, &self.config, on_token)?
+            }
+        };
+        Ok(finish(message, prepared, start))
+    }
+
+    // The requests for the first provider in the chain, as `generate` (or with `stream`,
+    // `generate_stream`) would post them. For a diff over the token budget those are the chunk
+    // summaries; should their replies still exceed the budget, later rounds are not shown. The
+    // API key is neither needed nor fetched.
+    pub fn dry_run(&self, stream: bool) -> Result<DryRun> {
+        let provider: Arc<dyn LlmProvider> = match &self.provider {
+            Some(provider) => provider.clone(),
+            None => Arc::from(llm::keyless_provider_from_config(&self.config)?),
+        };
+        let redaction = redact_diff(&self.diff, &self.config)?;
+        let budget = self.budget()?;
+
+        let mut bodies = Vec::new();
+        let mut estimated_tokens = 0;
+        let mut summary_chunks = 0;
+        if estimate_tokens(&redaction.text) > budget {
+            let chunks = split_diff(&redaction.text, budget);
+            summary_chunks = chunks.len();
+            for (i, chunk) in chunks.iter().enumerate() {
+                // summaries are never streamed
+                let messages = summary_messages(chunk, i + 1, chunks.len());
+                bodies.push(provider.request_body_with_limit(
+                    &messages,
+                    false,
+                    SUMMARY_MAX_OUTPUT_TOKENS,
+                )?);
+                estimated_tokens += estimate_messages(&messages);
+            }
+        } else {
+            let messages = llm::messages_from_config(&redaction.text, &self.history, &self.config)?;
+            bodies.push(provider.request_body(&messages, stream)?);
+            estimated_tokens = estimate_messages(&messages);
+        }
+
+        Ok(DryRun {
+            provider: provider.name().to_string(),
+            model: provider.model().to_string(),
+            bodies,
+            estimated_tokens,
+            summary_chunks,
+            redacted: redaction.counts,
+        })
+    }
+
+    // Secrets are masked before anything is sent. Diffs over the token budget are then split
+    // into chunks, each chunk is summarized in its own request and the summaries stand in for
+    // the diff
+    fn prepare_diff(&self) -> Result<PreparedDiff> {
+        let redaction = redact_diff(&self.diff, &self.config)?;
+        let budget = self.budget()?;
+        let mut prepared = PreparedDiff {
+            diff: redaction.text,
+            usage: None,
+            failures: Vec::new(),
+            redacted: redaction.counts,
+        };
+
+        for _ in 0..MAX_SUMMARY_ROUNDS {
+            if estimate_tokens(&prepared.diff) <= budget {
+                break;
+            }
+            let chunks = split_diff(&prepared.diff, budget);
+            let mut summaries = Vec::new();
+            for summary in self.summarize_chunks(&chunks)? {
+                prepared.usage = add_usage(prepared.usage, summary.usage);
+                prepared.failures.extend(summary.failures);
+                summaries.push(summary.text);
+            }
+            prepared.diff = join_summaries(&summaries);
+        }
+        Ok(prepared)
+    }
+
+    fn budget(&self) -> Result<usize> {
+        match self.diff_token_budget {
+            Some(budget) => Ok(budget),
+            None => diff_token_budget(&self.config),
+        }
+    }
+
+    // Requests the chunk summaries concurrently, at most `summary_concurrency` at a time, and
+    // returns them in chunk order. The providers use blocking HTTP, so every request runs on
+    // tokio's blocking pool and the semaphore keeps the number of open requests bounded. Once a
+    // summary fails no further chunk is sent, the requests already running are waited for.
+    // Blocking on a runtime of its own, this cannot be called from async code; such callers
+    // get an error instead of tokio's panic and should move the call to `spawn_blocking`.
+    fn summarize_chunks(&self, chunks: &[String]) -> Result<Vec<GeneratedMessage>> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(Error::Io(io::Error::other(
+                "CommitCrafter blocks, call it from outside the async runtime, e.g. in spawn_blocking",
+            )));
+        }
+        let limit = match self.summary_concurrency {
+            Some(limit) => limit,
+            None => summary_concurrency(&self.config)?,
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        runtime.block_on(async {
+            let semaphore = Arc::new(Semaphore::new(limit));
+            let failed = Arc::new(AtomicBool::new(false));
+            let mut tasks = JoinSet::new();
+
+            for (i, chunk) in chunks.iter().enumerate() {
+                let permit = semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| Error::Io(io::Error::other(e)))?;
+                // a failed request gives its permit back only after raising the flag
+                if failed.load(Ordering::SeqCst) {
+                    break;
+                }
+                let messages = summary_messages(chunk, i + 1, chunks.len());
+                let provider = self.provider.clone();
+                let config = self.config.clone();
+                let failed = failed.clone();
+                tasks.spawn_blocking(move || {
+                    let summary = summarize(provider.as_deref(), &config, &messages);
+                    if summary.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    drop(permit);
+                    (i, summary)
+                });
+            }
+
+            let mut summaries: Vec<Option<GeneratedMessage>> =
+                (0..chunks.len()).map(|_| None).collect();
+            let mut first_error = None;
+            while let Some(joined) = tasks.join_next().await {
+                let (i, summary) = match joined {
+                    Ok(result) => result,
+                    Err(e) => std::panic::resume_unwind(e.into_panic()),
+                };
+                match summary {
+                    Ok(summary) => summaries[i] = Some(summary),
+                    Err(e) => {
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+            match first_error {
+                Some(e) => Err(e),
+                None => Ok(summaries.into_iter().flatten().collect()),
+            }
+        })
+    }
+}
+
+fn summarize(
+    provider: Option<&dyn LlmProvider>,
+    config: &ConfigPaths,
+    messages: &[Message],
+) -> Result<GeneratedMessage> {
+    match provider {
+        Some(provider) => {
+            llm::generate_messages_with_provider(provider, messages, SUMMARY_MAX_OUTPUT_TOKENS)
+        }
+        None => llm::generate_messages(messages, SUMMARY_MAX_OUTPUT_TOKENS, config),
+    }
+}
+
+fn estimate_messages(messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .map(|message| estimate_tokens(&message.content))
+        .sum()
+}
+
+fn finish(
+    mut message: GeneratedMessage,
+    prepared: PreparedDiff,
+    start: Instant,
+) -> GeneratedMessage {
+    message.usage = add_usage(prepared.usage, message.usage);
+    message.latency = start.elapsed();
+    let mut failures = prepared.failures;
+    failures.append(&mut message.failures);
+    message.failures = failures;
+    message.redacted = prepared.redacted;
+    message
+}
+
+fn add_usage(total: Option<TokenUsage>, usage: Option<TokenUsage>) -> Option<TokenUsage> {
+    match (total, usage) {
+        (Some(total), Some(usage)) => Some(total + usage),
+        (total, usage) => total.or(usage),
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{
+    get_config_key, get_config_key_with_source, layer_file, ConfigPaths, VALID_API_KEY_COMMAND,
+    VALID_API_KEY_FILE,
+};
+use crate::error::{Error, Result};
+
+// `{provider}` in api_key_command or api_key_file is replaced by the provider name, so one
+// setting such as `pass show {provider}` serves every provider in the fallback chain
+const PROVIDER_PLACEHOLDER: &str = "{provider}";
+
+// How long api_key_command may take, long enough for a password manager to unlock
+pub const API_KEY_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Keys printed by api_key_command, by command line, so it runs once per process
+static COMMAND_KEYS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+// Config files already warned about
+static WARNED_FILES: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+// The API key for `provider`: `key` itself when set in any layer, otherwise the output of
+// api_key_command, otherwise the content of api_key_file. Empty when none of them is set
+pub fn api_key(provider: &str, key: &str, config: &ConfigPaths) -> Result<String> {
+    let (value, source) = get_config_key_with_source(&[key], config)?.remove(0);
+    if !value.is_empty() {
+        if let Some(file) = layer_file(config, &source) {
+            warn_if_world_readable(&file);
+        }
+        return Ok(value);
+    }
+
+    let values = get_config_key(&[VALID_API_KEY_COMMAND, VALID_API_KEY_FILE], config)?;
+    if !values[0].is_empty() {
+        return run_api_key_command(&values[0].replace(PROVIDER_PLACEHOLDER, provider));
+    }
+    if !values[1].is_empty() {
+        return read_api_key_file(&values[1].replace(PROVIDER_PLACEHOLDER, provider));
+    }
+    Ok(String::new())
+}
+
+// Runs `command` through the shell and returns its first line of output; the result is cached
+// for the rest of the process
+pub fn run_api_key_command(command: &str) -> Result<String> {
+    let mut cache = COMMAND_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(key) = cache.get(command) {
+        return Ok(key.clone());
+    }
+
+    let key = run_with_timeout(command, API_KEY_COMMAND_TIMEOUT)?;
+    cache.insert(command.to_string(), key.clone());
+    Ok(key)
+}
+
+fn run_with_timeout(command: &str, timeout: Duration) -> Result<String> {
+    let mut child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::MissingCredentials(format!("api_key_command failed: {}", e)))?;
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(Error::Timeout(format!(
+                "api_key_command did not finish within {} seconds",
+                timeout.as_secs()
+            )));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    if let Some(mut out) = child.stdout.take() {
+        out.read_to_string(&mut stdout)?;
+    }
+    if let Some(mut err) = child.stderr.take() {
+        err.read_to_string(&mut stderr)?;
+    }
+
+    if !status.success() {
+        return Err(Error::MissingCredentials(format!(
+            "api_key_command failed with {}: {}",
+            status,
+            stderr.trim()
+        )));
+    }
+    // pass and similar tools print the secret on the first line
+    let key = stdout.lines().next().unwrap_or_default().trim().to_string();
+    if key.is_empty() {
+        return Err(Error::MissingCredentials(
+            "api_key_command printed no key".to_string(),
+        ));
+    }
+    Ok(key)
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+// The key stored in `path`, which only its owner may read
+pub fn read_api_key_file(path: &str) -> Result<String> {
+    let path = expand_home(path);
+
+    let metadata = fs::metadata(&path).map_err(|e| Error::config_io(&path, e))?;
+    if let Some(mode) = mode(&metadata).filter(|mode| mode & 0o077 != 0) {
+        return Err(Error::invalid_value(
+            VALID_API_KEY_FILE,
+            format!(
+                "{} can be read by other users (mode {:o}), run chmod 600 on it",
+                path.display(),
+                mode
+            ),
+        ));
+    }
+
+    let contents = fs::read_to_string(&path).map_err(|e| Error::config_io(&path, e))?;
+    let key = contents
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if key.is_empty() {
+        return Err(Error::MissingCredentials(format!(
+            "{} holds no key",
+            path.display()
+        )));
+    }
+    Ok(key)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// A key in a config file that anyone on the machine can read is used, but not silently
+fn warn_if_world_readable(file: &Path) {
+    let mode = match fs::metadata(file).ok().as_ref().and_then(mode) {
+        Some(mode) if mode & 0o004 != 0 => mode,
+        _ => return,
+    };
+    let mut warned = WARNED_FILES.lock().unwrap_or_else(|e| e.into_inner());
+    if warned.insert(file.to_path_buf()) {
+        eprintln!(
+            "Warning: {} holds an API key and can be read by every user (mode {:o}), run chmod 600 on it or use api_key_command",
+            file.display(),
+            mode
+        );
+    }
+}
+
+// Permission bits, None where files have none
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
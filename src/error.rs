@@ -0,0 +1,143 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::llm::ProviderFailure;
+
+#[derive(Debug)]
+pub enum Error {
+    ConfigIo { path: PathBuf, source: io::Error },
+    ConfigParse { path: PathBuf, message: String },
+    InvalidKey(String),
+    InvalidValue { key: String, message: String },
+    InvalidLanguage(String),
+    MissingCredentials(String),
+    Git(String),
+    Io(io::Error),
+    SecretsFound(String),
+    Http(String),
+    Timeout(String),
+    ProviderStatus { status: u16, body: String },
+    ProviderResponse(String),
+    AllProvidersFailed(Vec<ProviderFailure>),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn config_io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::ConfigIo {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn config_parse(path: impl Into<PathBuf>, message: impl fmt::Display) -> Self {
+        Error::ConfigParse {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn invalid_value(key: &str, message: impl fmt::Display) -> Self {
+        Error::InvalidValue {
+            key: key.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    // Errors that another provider in the fallback chain could avoid
+    pub fn should_fall_back(&self) -> bool {
+        match self {
+            Error::MissingCredentials(_) | Error::Http(_) | Error::Timeout(_) => true,
+            Error::ProviderStatus { status, .. } => matches!(status, 401 | 403 | 429 | 500..=599),
+            _ => false,
+        }
+    }
+
+    // Process exit status for the binary, 124 matches timeout(1) and is checked by the hook
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::ConfigIo { .. } => 10,
+            Error::ConfigParse { .. } => 11,
+            Error::InvalidKey(_) => 12,
+            Error::InvalidValue { .. } => 13,
+            Error::InvalidLanguage(_) => 14,
+            Error::MissingCredentials(_) => 15,
+            Error::Git(_) => 20,
+            Error::Io(_) => 21,
+            Error::SecretsFound(_) => 22,
+            Error::Http(_) => 30,
+            Error::ProviderStatus { .. } => 31,
+            Error::ProviderResponse(_) => 32,
+            // a chain in which every provider timed out is reported like a single timeout
+            Error::AllProvidersFailed(failures)
+                if !failures.is_empty()
+                    && failures
+                        .iter()
+                        .all(|failure| matches!(failure.error, Error::Timeout(_))) =>
+            {
+                124
+            }
+            Error::AllProvidersFailed(_) => 33,
+            Error::Timeout(_) => 124,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ConfigIo { path, source } => {
+                write!(f, "Could not access {}: {}", path.display(), source)
+            }
+            Error::ConfigParse { path, message } => {
+                write!(f, "Could not parse {}: {}", path.display(), message)
+            }
+            Error::InvalidKey(key) => write!(f, "Invalid configuration key: {}", key),
+            Error::InvalidValue { key, message } => write!(f, "Invalid {}: {}", key, message),
+            Error::InvalidLanguage(language) => write!(f, "Invalid user language: {}", language),
+            Error::MissingCredentials(msg) => write!(f, "{}", msg),
+            Error::Git(msg) => write!(f, "{}", msg),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::SecretsFound(found) => write!(
+                f,
+                "Secrets found in the diff ({}), nothing was sent. Set secret_action to redact to send the diff with them masked",
+                found
+            ),
+            Error::Http(msg) => write!(f, "HTTP request failed: {}", msg),
+            Error::Timeout(msg) => write!(f, "Request timed out: {}", msg),
+            Error::ProviderStatus { status, body } => {
+                write!(f, "Request failed with status code: {}", status)?;
+                if !body.is_empty() {
+                    write!(f, " ({})", body)?;
+                }
+                Ok(())
+            }
+            Error::ProviderResponse(msg) => write!(f, "Could not parse response: {}", msg),
+            Error::AllProvidersFailed(failures) => {
+                write!(f, "All providers failed")?;
+                for failure in failures {
+                    write!(f, "\n  {}: {}", failure.provider, failure.error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ConfigIo { source, .. } => Some(source),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
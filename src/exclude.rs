@@ -0,0 +1,228 @@
+use std::fs;
+use std::path::Path;
+
+use crate::config::{get_config_key, ConfigPaths, VALID_EXCLUDE_PATTERNS};
+use crate::error::{Error, Result};
+use crate::git_integration;
+
+pub const IGNORE_FILE: &str = ".commitcrafterignore";
+
+// Files whose content rarely helps the model; `!pattern` in the config or the ignore file
+// brings any of them back
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    // lockfiles
+    "*.lock",
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "bun.lockb",
+    "composer.lock",
+    "Gemfile.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "go.sum",
+    // minified assets and source maps
+    "*.min.js",
+    "*.min.css",
+    "*.map",
+    // test snapshots
+    "*.snap",
+    "__snapshots__/",
+    // generated code
+    "*.pb.go",
+    "*_pb2.py",
+    "*_pb2_grpc.py",
+    "*.generated.*",
+    "*.g.dart",
+];
+
+// gitignore-style rules; the last rule matching a path decides whether it is excluded
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    negated: bool,
+    dir_only: bool,
+    segments: Vec<String>,
+}
+
+impl ExcludeRules {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let mut rules = ExcludeRules::default();
+        rules.extend(patterns);
+        rules
+    }
+
+    pub fn with_defaults() -> Self {
+        ExcludeRules::new(DEFAULT_EXCLUDES)
+    }
+
+    // Built-in defaults, then the exclude_patterns config key, then the repository's
+    // .commitcrafterignore
+    pub fn load(config: &ConfigPaths) -> Result<Self> {
+        let mut rules = ExcludeRules::with_defaults();
+        let values = get_config_key(&[VALID_EXCLUDE_PATTERNS], config)?;
+        rules.extend(&split_patterns(&values[0]));
+
+        if let Ok(git_root) = git_integration::get_git_root_dir() {
+            let ignore_file = git_root.join(IGNORE_FILE);
+            if ignore_file.exists() {
+                rules.extend_from_file(&ignore_file)?;
+            }
+        }
+        Ok(rules)
+    }
+
+    pub fn extend<S: AsRef<str>>(&mut self, patterns: &[S]) {
+        self.rules.extend(
+            patterns
+                .iter()
+                .filter_map(|pattern| Rule::parse(pattern.as_ref())),
+        );
+    }
+
+    pub fn extend_from_file(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path).map_err(|e| Error::config_io(path, e))?;
+        self.extend(&content.lines().collect::<Vec<&str>>());
+        Ok(())
+    }
+
+    // `path` is relative to the repository root, with `/` separators
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+// The config key holds a comma separated list
+pub fn split_patterns(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        if pattern.is_empty() {
+            return None;
+        }
+
+        // without a slash the pattern matches at any depth, otherwise it is anchored to the root
+        let mut segments: Vec<String> = Vec::new();
+        if !pattern.contains('/') {
+            segments.push("**".to_string());
+        }
+        segments.extend(
+            pattern
+                .trim_start_matches('/')
+                .split('/')
+                .map(|segment| segment.to_string()),
+        );
+
+        Some(Rule {
+            negated,
+            dir_only,
+            segments,
+        })
+    }
+
+    // A rule matching a directory also matches everything beneath it
+    fn matches(&self, path: &str) -> bool {
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        (1..=components.len())
+            .filter(|&end| !(self.dir_only && end == components.len()))
+            .any(|end| match_segments(&self.segments, &components[..end]))
+    }
+}
+
+fn match_segments(segments: &[String], components: &[&str]) -> bool {
+    match segments.split_first() {
+        None => components.is_empty(),
+        Some((segment, rest)) if segment == "**" => {
+            (0..=components.len()).any(|skip| match_segments(rest, &components[skip..]))
+        }
+        Some((segment, rest)) => match components.split_first() {
+            Some((component, remaining)) => {
+                let pattern: Vec<char> = segment.chars().collect();
+                let name: Vec<char> = component.chars().collect();
+                match_component(&pattern, &name) && match_segments(rest, remaining)
+            }
+            None => false,
+        },
+    }
+}
+
+// `*`, `?`, `[a-z]`, `[!a-z]` and `\` escapes within a single path component
+fn match_component(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| match_component(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && match_component(&pattern[1..], &name[1..]),
+        Some('[') => match (name.first(), match_class(&pattern[1..])) {
+            (Some(&c), Some((matches, len))) => {
+                matches(c) && match_component(&pattern[len + 1..], &name[1..])
+            }
+            // an unterminated class is a literal `[`
+            (Some('['), None) => match_component(&pattern[1..], &name[1..]),
+            _ => false,
+        },
+        Some('\\') if pattern.len() > 1 => {
+            name.first() == Some(&pattern[1]) && match_component(&pattern[2..], &name[1..])
+        }
+        Some(&c) => name.first() == Some(&c) && match_component(&pattern[1..], &name[1..]),
+    }
+}
+
+// Parses the class after `[`, returning a predicate and the number of chars up to and
+// including the closing `]`
+fn match_class(pattern: &[char]) -> Option<(impl Fn(char) -> bool, usize)> {
+    let (negated, start) = match pattern.first() {
+        Some('!') | Some('^') => (true, 1),
+        _ => (false, 0),
+    };
+    // a `]` right after the opening bracket is part of the class
+    let close = pattern
+        .iter()
+        .skip(start + 1)
+        .position(|&c| c == ']')
+        .map(|i| i + start + 1)?;
+
+    let class: Vec<char> = pattern[start..close].to_vec();
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            ranges.push((class[i], class[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((class[i], class[i]));
+            i += 1;
+        }
+    }
+
+    let matches = move |c: char| ranges.iter().any(|&(low, high)| low <= c && c <= high) != negated;
+    Some((matches, close + 1))
+}
@@ -1,111 +1,700 @@
-use std::io::{self, ErrorKind};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use crate::config::{get_config_key, ConfigPaths, VALID_RENAME_THRESHOLD};
+use crate::error::{Error, Result};
+use crate::exclude::ExcludeRules;
+
+// The parser expects plain text with a/ and b/ prefixes, whatever diff.external, color.diff,
+// diff.noprefix or diff.mnemonicPrefix the user has configured
+const DIFF_ARGS: [&str; 7] = [
+    "diff",
+    "--no-ext-diff",
+    "--no-color",
+    "--src-prefix=a/",
+    "--dst-prefix=b/",
+    "--ignore-all-space",
+    "--diff-algorithm=minimal",
+];
+
+// git hashes every repository's empty tree to this id, the parent side of a root commit
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+// Which changes to describe
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DiffSource {
+    #[default]
+    Staged,
+    // working tree against the index
+    Unstaged,
+    // working tree against HEAD, staged or not
+    All,
+    // an existing commit against its first parent
+    Commit(String),
+    // `A..B` or `A...B`
+    Range(String),
+    // the text of a patch from a file or stdin, git is not run
+    Patch(String),
+}
+
+// What goes into the diff and how git compares it
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    pub source: DiffSource,
+    pub excludes: ExcludeRules,
+    // minimum similarity in percent for a delete and an add to count as a rename or copy,
+    // 0 turns detection off
+    pub rename_threshold: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStat {
+    pub path: String,
+    // set when git detected a rename or copy
+    pub old_path: Option<String>,
+    // None for binary files
+    pub additions: Option<u64>,
+    pub deletions: Option<u64>,
+}
+
+// Changed lines above which a generated file is summarized instead of shown
+pub const GENERATED_LINE_LIMIT: u64 = 500;
+
+// Lines this long only come out of minifiers and code generators
+const GENERATED_LINE_LENGTH: usize = 1000;
+
+// Markers code generators leave near the top of their output
+const GENERATED_MARKERS: [&str; 5] = [
+    "@generated",
+    "DO NOT EDIT",
+    "Code generated",
+    "auto-generated",
+    "autogenerated",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Copied,
+}
+
+// One file of the staged change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: FileStatus,
+    pub additions: u64,
+    pub deletions: u64,
+    pub binary: bool,
+    pub generated: bool,
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    // rename or copy similarity in percent
+    pub similarity: Option<u32>,
+    // the file's part of the unified diff, header included
+    pub patch: String,
+}
+
+// Every changed file: the files that go to the model and the excluded files that are only
+// named
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeSet {
+    pub files: Vec<FileChange>,
+    pub excluded: Vec<FileStat>,
+    // a patch without git headers, such as `diff -u` output, sent as it is
+    pub unparsed: String,
+}
+
 pub fn exclude_from_diff(path: &str) -> String {
     format!(":(exclude){}", path)
 }
 
-pub fn run_git_diff() -> Result<String, io::Error> {
-    let files_to_exclude = vec![
-        "Cargo.lock",
-        "pakcage-lock.json",
-        "pnpm-lock.yaml",
-        "*.lock",
-    ];
+// Matches exactly one path relative to the repository root, whatever the working directory
+fn exclude_literal(path: &str) -> String {
+    format!(":(exclude,literal,top){}", path)
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            source: DiffSource::Staged,
+            excludes: ExcludeRules::with_defaults(),
+            rename_threshold: 50,
+        }
+    }
+}
+
+impl DiffOptions {
+    pub fn load(config: &ConfigPaths) -> Result<Self> {
+        let values = get_config_key(&[VALID_RENAME_THRESHOLD], config)?;
+        let rename_threshold = values[0].parse::<u32>().map_err(|_| {
+            Error::invalid_value(
+                VALID_RENAME_THRESHOLD,
+                format!("expected a percentage, got {}", values[0]),
+            )
+        })?;
+
+        Ok(DiffOptions {
+            source: DiffSource::Staged,
+            excludes: ExcludeRules::load(config)?,
+            rename_threshold: rename_threshold.min(100),
+        })
+    }
+
+    // `-M`/`-C` so moved and copied files show only their content delta
+    fn rename_args(&self) -> Vec<String> {
+        if self.rename_threshold == 0 {
+            return vec!["--no-renames".to_string()];
+        }
+        vec![
+            format!("-M{}%", self.rename_threshold),
+            format!("-C{}%", self.rename_threshold),
+        ]
+    }
+}
+
+impl DiffSource {
+    // The revisions to pass to `git diff`
+    pub fn revisions(&self) -> Result<Vec<String>> {
+        match self {
+            DiffSource::Staged => Ok(vec!["--staged".to_string()]),
+            DiffSource::Unstaged => Ok(Vec::new()),
+            DiffSource::All => Ok(vec!["HEAD".to_string()]),
+            DiffSource::Commit(rev) => {
+                let commit = resolve_revision(&format!("{}^{{commit}}", rev))
+                    .map_err(|_| Error::Git(format!("Unknown commit {}", rev)))?;
+                let parent = match resolve_revision(&format!("{}^", commit)) {
+                    Ok(parent) => parent,
+                    Err(_) => EMPTY_TREE.to_string(),
+                };
+                Ok(vec![parent, commit])
+            }
+            DiffSource::Range(range) => {
+                if !range.contains("..") {
+                    return Err(Error::Git(format!(
+                        "Invalid range {}, expected A..B or A...B",
+                        range
+                    )));
+                }
+                Ok(vec![range.clone()])
+            }
+            DiffSource::Patch(_) => Err(Error::Git("A patch is not compared with git".to_string())),
+        }
+    }
+
+    // The commit whose history the message should follow: for an existing commit or a range
+    // the commits after it would give the answer away
+    pub fn history_base(&self) -> Option<String> {
+        match self {
+            DiffSource::Commit(rev) => Some(format!("{}^", rev)),
+            DiffSource::Range(range) => range
+                .split("..")
+                .next()
+                .filter(|base| !base.is_empty())
+                .map(|base| base.to_string()),
+            _ => None,
+        }
+    }
+}
+
+// The change as it is sent to the model, see `ChangeSet::render`
+pub fn run_git_diff(options: &DiffOptions) -> Result<String> {
+    Ok(collect_changes(options)?.render())
+}
+
+pub fn collect_changes(options: &DiffOptions) -> Result<ChangeSet> {
+    if let DiffSource::Patch(patch) = &options.source {
+        return Ok(ChangeSet::from_patch(patch, &options.excludes));
+    }
+
+    let revisions = options.source.revisions()?;
+    let stats = numstat(options, &revisions)?;
 
-    let exclude_path: Vec<String> = files_to_exclude
+    let mut command = Command::new("git");
+    command
+        .args(["-c", "core.quotePath=false"])
+        .args(DIFF_ARGS)
+        .args(options.rename_args())
+        .args(&revisions);
+    let excluded: Vec<&FileStat> = stats
         .iter()
-        .map(|path| exclude_from_diff(path))
+        .filter(|stat| options.excludes.is_excluded(&stat.path))
         .collect();
+    if !excluded.is_empty() {
+        command.arg("--");
+        for stat in excluded {
+            command.arg(exclude_literal(&stat.path));
+            if let Some(old_path) = &stat.old_path {
+                command.arg(exclude_literal(old_path));
+            }
+        }
+    }
+
+    let diff = run_git(&mut command, "Error running git diff")?;
+    Ok(ChangeSet::parse(&diff, &stats, &options.excludes))
+}
 
+fn numstat(options: &DiffOptions, revisions: &[String]) -> Result<Vec<FileStat>> {
     let mut command = Command::new("git");
-    command.args(&[
-        "diff",
-        "--staged",
-        "--ignore-all-space",
-        "--diff-algorithm=minimal",
-    ]);
+    command
+        .args(DIFF_ARGS)
+        .args(options.rename_args())
+        .args(["--numstat", "-z"])
+        .args(revisions);
+    let output = run_git(&mut command, "Error running git diff --numstat")?;
+    Ok(parse_numstat(&output))
+}
+
+// Parses `git diff --numstat -z`: `added\tdeleted\tpath\0`, or for renames and copies
+// `added\tdeleted\t\0old\0new\0`; binary files count `-` lines
+pub fn parse_numstat(output: &str) -> Vec<FileStat> {
+    let mut stats = Vec::new();
+    let mut fields = output.split('\0');
+
+    while let Some(record) = fields.next() {
+        let mut parts = record.splitn(3, '\t');
+        let (Some(additions), Some(deletions), Some(path)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+
+        let (old_path, path) = if path.is_empty() {
+            match (fields.next(), fields.next()) {
+                (Some(old_path), Some(new_path)) => (Some(old_path.to_string()), new_path),
+                _ => break,
+            }
+        } else {
+            (None, path)
+        };
+
+        stats.push(FileStat {
+            path: path.to_string(),
+            old_path,
+            additions: additions.parse().ok(),
+            deletions: deletions.parse().ok(),
+        });
+    }
+    stats
+}
+
+pub fn format_excluded(excluded: &[&FileStat]) -> String {
+    let mut listing = String::from("\nExcluded from the diff, content not shown:\n");
+    for stat in excluded {
+        match (stat.additions, stat.deletions) {
+            (Some(additions), Some(deletions)) => {
+                listing.push_str(&format!("{} (+{} -{})\n", stat.path, additions, deletions))
+            }
+            _ => listing.push_str(&format!("{} (binary)\n", stat.path)),
+        }
+    }
+    listing
+}
+
+impl FileStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileStatus::Added => "added",
+            FileStatus::Modified => "modified",
+            FileStatus::Deleted => "deleted",
+            FileStatus::Renamed => "renamed",
+            FileStatus::Copied => "copied",
+        }
+    }
+}
+
+impl FileChange {
+    fn new(path: &str) -> Self {
+        FileChange {
+            path: path.to_string(),
+            old_path: None,
+            status: FileStatus::Modified,
+            additions: 0,
+            deletions: 0,
+            binary: false,
+            generated: false,
+            old_mode: None,
+            new_mode: None,
+            similarity: None,
+            patch: String::new(),
+        }
+    }
+
+    // Binary files and large generated files are described in one line instead of shown
+    pub fn is_summarized(&self) -> bool {
+        self.binary || (self.generated && self.additions + self.deletions > GENERATED_LINE_LIMIT)
+    }
+
+    pub fn summary(&self) -> String {
+        let name = match &self.old_path {
+            Some(old_path) => format!("{} -> {}", old_path, self.path),
+            None => self.path.clone(),
+        };
+        let mut details = vec![self.status.as_str().to_string()];
+        if self.binary {
+            details.push("binary".to_string());
+        } else {
+            if self.generated {
+                details.push("generated".to_string());
+            }
+            details.push(format!("+{} -{}", self.additions, self.deletions));
+        }
+        if let (Some(old_mode), Some(new_mode)) = (&self.old_mode, &self.new_mode) {
+            details.push(format!("mode {} -> {}", old_mode, new_mode));
+        }
+        format!("{} ({})", name, details.join(", "))
+    }
+
+    // Renamed and copied files are shown as `old -> new` followed by their hunks only, so a
+    // move costs one line instead of the whole file twice
+    pub fn render(&self) -> String {
+        let Some(old_path) = &self.old_path else {
+            return self.patch.clone();
+        };
+
+        let mut text = format!("{} {} -> {}", self.status.as_str(), old_path, self.path);
+        if let Some(similarity) = self.similarity {
+            text.push_str(&format!(" ({}% similar)", similarity));
+        }
+        if let (Some(old_mode), Some(new_mode)) = (&self.old_mode, &self.new_mode) {
+            text.push_str(&format!(", mode {} -> {}", old_mode, new_mode));
+        }
+        text.push('\n');
+        if let Some(start) = self.patch.find("\n@@") {
+            text.push_str(&self.patch[start + 1..]);
+        }
+        text
+    }
+}
+
+// The new path from the first line `FileChange::render` writes for a renamed or copied file,
+// which stands in for its `diff --git` header
+pub fn rendered_rename_path(line: &str) -> Option<&str> {
+    let rest = line
+        .strip_prefix("renamed ")
+        .or_else(|| line.strip_prefix("copied "))?;
+    let (_, new_path) = rest.split_once(" -> ")?;
+    let end = [" (", ", mode "]
+        .iter()
+        .filter_map(|suffix| new_path.find(suffix))
+        .min()
+        .unwrap_or(new_path.len());
+    Some(&new_path[..end])
+}
 
-    for path in exclude_path {
-        command.arg(path);
-    }
-
-    let output = command.output();
-
-    match output {
-        Ok(output) if output.status.success() => match String::from_utf8(output.stdout) {
-            Ok(output_str) => Ok(output_str),
-            Err(e) => Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!("Output is not valid UTF-8: {}", e),
-            )),
-        },
-        Ok(output) => {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(io::Error::new(
-                ErrorKind::Other,
-                format!("Error: {}", stderr),
-            ))
-        }
-        Err(e) => Err(e),
-    }
-}
-
-pub fn get_recent_commits(count: usize) -> Result<Vec<String>, io::Error> {
-    let command = Command::new("git")
-        .args(&["log", &format!("-{}", count), "--pretty=format:%s"])
-        .output();
-
-    match command {
-        Ok(output) if output.status.success() => match String::from_utf8(output.stdout) {
-            Ok(output_str) => {
-                let commits: Vec<String> = output_str
-                    .lines()
-                    .map(|line| line.trim().to_string())
-                    .filter(|line| !line.is_empty())
-                    .collect();
-                Ok(commits)
+impl ChangeSet {
+    // Builds the change from the unified diff of the files that were not excluded and the
+    // numstat of every staged file
+    pub fn parse(diff: &str, stats: &[FileStat], excludes: &ExcludeRules) -> Self {
+        let mut files = parse_diff(diff);
+        for file in &mut files {
+            if let Some(stat) = stats.iter().find(|stat| stat.path == file.path) {
+                match (stat.additions, stat.deletions) {
+                    (Some(additions), Some(deletions)) => {
+                        file.additions = additions;
+                        file.deletions = deletions;
+                    }
+                    _ => file.binary = true,
+                }
             }
-            Err(e) => Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!("Output is not valid UTF-8: {}", e),
-            )),
-        },
-        Ok(output) => {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(io::Error::new(
-                ErrorKind::Other,
-                format!("Error getting recent commits: {}", stderr),
-            ))
-        }
-        Err(e) => Err(e),
-    }
-}
-
-pub fn get_git_root_dir() -> Result<PathBuf, io::Error> {
-    let command = Command::new("git")
-        .args(&["rev-parse", "--show-toplevel"])
-        .output();
-
-    match command {
-        Ok(output) if output.status.success() => match String::from_utf8(output.stdout) {
-            Ok(output_str) => {
-                let root_path = output_str.trim();
-                Ok(PathBuf::from(root_path))
+        }
+
+        ChangeSet {
+            files,
+            excluded: stats
+                .iter()
+                .filter(|stat| excludes.is_excluded(&stat.path))
+                .cloned()
+                .collect(),
+            unparsed: String::new(),
+        }
+    }
+
+    // A patch read from elsewhere, `git diff` or `git format-patch` output. Without numstat the
+    // line counts come from the hunks, and mail headers before the first file are dropped so
+    // an existing commit message does not leak into the prompt.
+    pub fn from_patch(patch: &str, excludes: &ExcludeRules) -> Self {
+        let (excluded, files): (Vec<FileChange>, Vec<FileChange>) = parse_diff(patch)
+            .into_iter()
+            .partition(|file| excludes.is_excluded(&file.path));
+        if files.is_empty() && excluded.is_empty() {
+            return ChangeSet {
+                unparsed: patch.to_string(),
+                ..ChangeSet::default()
+            };
+        }
+
+        ChangeSet {
+            files,
+            excluded: excluded
+                .into_iter()
+                .map(|file| FileStat {
+                    additions: (!file.binary).then_some(file.additions),
+                    deletions: (!file.binary).then_some(file.deletions),
+                    path: file.path,
+                    old_path: file.old_path,
+                })
+                .collect(),
+            unparsed: String::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.excluded.is_empty() && self.unparsed.trim().is_empty()
+    }
+
+    // The diffs of the regular files, then one line for every summarized file, then the names
+    // of the excluded files
+    pub fn render(&self) -> String {
+        let mut text = self.unparsed.clone();
+        for file in self.files.iter().filter(|file| !file.is_summarized()) {
+            text.push_str(&file.render());
+        }
+
+        let summarized: Vec<&FileChange> = self
+            .files
+            .iter()
+            .filter(|file| file.is_summarized())
+            .collect();
+        if !summarized.is_empty() {
+            text.push_str("\nSummarized, content not shown:\n");
+            for file in summarized {
+                text.push_str(&file.summary());
+                text.push('\n');
             }
-            Err(e) => Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!("Output is not valid UTF-8: {}", e),
-            )),
-        },
-        Ok(output) => {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(io::Error::new(
-                ErrorKind::Other,
-                format!("Error getting git root directory: {}", stderr),
-            ))
-        }
-        Err(e) => Err(e),
+        }
+
+        if !self.excluded.is_empty() {
+            text.push_str(&format_excluded(
+                &self.excluded.iter().collect::<Vec<&FileStat>>(),
+            ));
+        }
+        text
+    }
+}
+
+// Splits a unified diff into its files and reads status, paths, modes and line counts from
+// the extended headers and hunks; anything before the first `diff --git` line is skipped
+pub fn parse_diff(diff: &str) -> Vec<FileChange> {
+    let mut sections: Vec<String> = Vec::new();
+    for line in diff.split_inclusive('\n') {
+        match sections.last_mut() {
+            _ if line.starts_with("diff --git ") => sections.push(line.to_string()),
+            Some(section) => section.push_str(line),
+            None => {}
+        }
+    }
+    sections.iter().map(|section| parse_file(section)).collect()
+}
+
+fn parse_file(section: &str) -> FileChange {
+    let header = section.lines().next().unwrap_or_default();
+    let mut file = FileChange::new(&header_path(header));
+    file.patch = section.to_string();
+
+    let mut in_hunk = false;
+    let mut hunk_lines = 0;
+    for line in section.lines().skip(1) {
+        if in_hunk {
+            if line.starts_with("@@") {
+                continue;
+            }
+            if line.starts_with('+') {
+                file.additions += 1;
+            } else if line.starts_with('-') {
+                file.deletions += 1;
+            }
+            // markers only count near the top of the file, long lines anywhere
+            if (hunk_lines < 10 && GENERATED_MARKERS.iter().any(|marker| line.contains(marker)))
+                || line.len() > GENERATED_LINE_LENGTH
+            {
+                file.generated = true;
+            }
+            hunk_lines += 1;
+            continue;
+        }
+
+        if line.starts_with("@@") {
+            in_hunk = true;
+        } else if let Some(mode) = line.strip_prefix("new file mode ") {
+            file.status = FileStatus::Added;
+            file.new_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+            file.status = FileStatus::Deleted;
+            file.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("old mode ") {
+            file.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            file.new_mode = Some(mode.to_string());
+        } else if let Some(similarity) = line.strip_prefix("similarity index ") {
+            file.similarity = similarity.trim_end_matches('%').parse().ok();
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            file.status = FileStatus::Renamed;
+            file.old_path = Some(unquote(path));
+        } else if let Some(path) = line.strip_prefix("copy from ") {
+            file.status = FileStatus::Copied;
+            file.old_path = Some(unquote(path));
+        } else if let Some(path) = line
+            .strip_prefix("rename to ")
+            .or_else(|| line.strip_prefix("copy to "))
+        {
+            file.path = unquote(path);
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if path != "/dev/null" {
+                let path = unquote(path.trim_end_matches('\t'));
+                file.path = path.strip_prefix("b/").unwrap_or(&path).to_string();
+            }
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            file.binary = true;
+        }
+    }
+    file
+}
+
+// `diff --git a/path b/path`; both sides are equal unless the file was renamed, in which case
+// the `rename to` line gives the path
+fn header_path(header: &str) -> String {
+    let names = header.strip_prefix("diff --git ").unwrap_or(header);
+    if let Some(end) = names.strip_prefix('"').and_then(|rest| rest.find("\" ")) {
+        let path = unquote(names[end + 3..].trim());
+        return path.strip_prefix("b/").unwrap_or(&path).to_string();
     }
+
+    let half = names.len() / 2;
+    if names.len() % 2 == 1 && names.is_char_boundary(half) && names[..half].starts_with("a/") {
+        let (old, new) = (&names[2..half], &names[half + 1..]);
+        if new.strip_prefix("b/") == Some(old) {
+            return old.to_string();
+        }
+    }
+    match names.find(" b/") {
+        Some(i) => names[i + 3..].to_string(),
+        None => names.to_string(),
+    }
+}
+
+// git quotes paths with unusual characters in C style
+fn unquote(path: &str) -> String {
+    let Some(inner) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unquoted.push('\t'),
+            Some('n') => unquoted.push('\n'),
+            Some(other) => unquoted.push(other),
+            None => {}
+        }
+    }
+    unquoted
+}
+
+pub fn get_recent_commits(count: usize) -> Result<Vec<String>> {
+    get_recent_commits_from("HEAD", count)
+}
+
+// The subjects of `rev` and the commits before it
+pub fn get_recent_commits_from(rev: &str, count: usize) -> Result<Vec<String>> {
+    let mut command = Command::new("git");
+    command.args([
+        "log",
+        &format!("-{}", count),
+        "--pretty=format:%s",
+        rev,
+        "--",
+    ]);
+
+    let output_str = run_git(&mut command, "Error getting recent commits")?;
+    let commits: Vec<String> = output_str
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    Ok(commits)
+}
+
+pub fn get_git_root_dir() -> Result<PathBuf> {
+    let mut command = Command::new("git");
+    command.args(["rev-parse", "--show-toplevel"]);
+
+    let output_str = run_git(&mut command, "Error getting git root directory")?;
+    Ok(PathBuf::from(output_str.trim()))
+}
+
+// The first commit of the repository at `git_root`, the same in every clone wherever it is
+// checked out; the smallest id when unrelated histories were merged. A shallow clone does not
+// have it, the oldest commit it holds moves whenever the clone is deepened
+pub fn get_root_commit(git_root: &Path) -> Result<String> {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(git_root)
+        .args(["rev-parse", "--is-shallow-repository"]);
+    if run_git(&mut command, "Error checking for a shallow clone")?.trim() == "true" {
+        return Err(Error::Git(
+            "Error getting root commit: shallow clone".to_string(),
+        ));
+    }
+
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(git_root)
+        .args(["rev-list", "--max-parents=0", "HEAD"]);
+
+    let output_str = run_git(&mut command, "Error getting root commit")?;
+    output_str
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .min()
+        .map(str::to_string)
+        .ok_or_else(|| Error::Git("Error getting root commit: no commits yet".to_string()))
+}
+
+// The URL of the origin remote of the repository at `git_root`
+pub fn get_origin_url(git_root: &Path) -> Result<String> {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(git_root)
+        .args(["remote", "get-url", "origin"]);
+
+    let url = run_git(&mut command, "Error getting origin URL")?;
+    match url.trim() {
+        "" => Err(Error::Git("Error getting origin URL: empty".to_string())),
+        url => Ok(url.to_string()),
+    }
+}
+
+fn resolve_revision(rev: &str) -> Result<String> {
+    let mut command = Command::new("git");
+    command.args(["rev-parse", "--verify", "--quiet", rev]);
+    let output = run_git(&mut command, "Error resolving revision")
+        .map_err(|_| Error::Git(format!("Unknown revision {}", rev)))?;
+    Ok(output.trim().to_string())
+}
+
+fn run_git(command: &mut Command, context: &str) -> Result<String> {
+    let output = command
+        .output()
+        .map_err(|e| Error::Git(format!("{}: {}", context, e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Git(format!("{}: {}", context, stderr.trim())));
+    }
+
+    String::from_utf8(output.stdout)
+        .map_err(|e| Error::Git(format!("{}: output is not valid UTF-8: {}", context, e)))
 }
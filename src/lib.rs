@@ -1,5 +1,16 @@
+pub mod chunking;
 pub mod config;
+pub mod crafter;
+pub mod credentials;
+pub mod error;
+pub mod exclude;
 pub mod git_integration;
 pub mod install;
-pub mod uninstall;
 pub mod llm;
+pub mod project;
+pub mod redact;
+pub mod uninstall;
+
+pub use crafter::{CommitCrafter, DryRun};
+pub use error::{Error, Result};
+pub use llm::{GeneratedMessage, TokenUsage};
@@ -0,0 +1,221 @@
+use reqwest::blocking::Response;
+use serde_json::{json, Value};
+use std::io::BufReader;
+
+use super::http::{body_error, HttpClient};
+use super::sse::read_sse_stream;
+use super::{Completion, LlmProvider, Message, TokenUsage, MAX_OUTPUT_TOKENS, PROVIDER_ANTHROPIC};
+use crate::config::{
+    get_config_key, ConfigPaths, VALID_ANTHROPIC_API_KEY, VALID_ANTHROPIC_MODEL,
+    VALID_ANTHROPIC_URL,
+};
+use crate::credentials::api_key;
+use crate::error::{Error, Result};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+pub struct AnthropicProvider {
+    api_key: String,
+    url: String,
+    model: String,
+    http: HttpClient,
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: &str, url: &str, model: &str) -> Self {
+        AnthropicProvider {
+            api_key: api_key.to_string(),
+            url: url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            http: HttpClient::default(),
+        }
+    }
+
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    pub fn from_config(config: &ConfigPaths) -> Result<Self> {
+        let api_key = api_key(PROVIDER_ANTHROPIC, VALID_ANTHROPIC_API_KEY, config)?;
+        if api_key.is_empty() {
+            return Err(Error::MissingCredentials(
+                "Anthropic API key or URL is empty".to_string(),
+            ));
+        }
+        AnthropicProvider::from_config_with_key(&api_key, config)
+    }
+
+    pub fn from_config_with_key(api_key: &str, config: &ConfigPaths) -> Result<Self> {
+        let keys = [VALID_ANTHROPIC_URL, VALID_ANTHROPIC_MODEL];
+        let values = get_config_key(&keys, config)?;
+
+        if values[0].is_empty() {
+            return Err(Error::MissingCredentials(
+                "Anthropic API key or URL is empty".to_string(),
+            ));
+        }
+
+        Ok(AnthropicProvider::new(api_key, &values[0], &values[1])
+            .with_http_client(HttpClient::from_config(config)?))
+    }
+
+    fn body(&self, messages: &[Message], stream: bool, max_output_tokens: u32) -> Result<Value> {
+        // the Messages API takes the system prompt as a top-level field
+        let system: Vec<&str> = messages
+            .iter()
+            .filter(|message| message.role == "system")
+            .map(|message| message.content.as_str())
+            .collect();
+        let messages: Vec<Value> = messages
+            .iter()
+            .filter(|message| message.role != "system")
+            .map(|message| json!({ "role": message.role, "content": message.content }))
+            .collect();
+        let mut body = json!({
+            "model": self.model,
+            "system": system.join("\n\n"),
+            "messages": messages,
+            "max_tokens": max_output_tokens
+        });
+        if stream {
+            body["stream"] = json!(true);
+        }
+        Ok(body)
+    }
+
+    fn send(&self, body: &Value) -> Result<Response> {
+        let response = self.http.send(|client| {
+            client
+                .post(format!("{}/v1/messages", self.url))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .json(body)
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().unwrap_or_default();
+            let body = match serde_json::from_str::<Value>(&body) {
+                Ok(error_json) => error_message(&error_json).unwrap_or(body),
+                Err(_) => body,
+            };
+            return Err(Error::ProviderStatus {
+                status: status.as_u16(),
+                body,
+            });
+        }
+        Ok(response)
+    }
+}
+
+// error bodies look like {"type": "error", "error": {"type": ..., "message": ...}}
+fn error_message(error_json: &Value) -> Option<String> {
+    match (
+        error_json["error"]["type"].as_str(),
+        error_json["error"]["message"].as_str(),
+    ) {
+        (Some(kind), Some(message)) => Some(format!("{}: {}", kind, message)),
+        _ => None,
+    }
+}
+
+fn usage(usage: &Value) -> Option<TokenUsage> {
+    Some(TokenUsage {
+        input_tokens: usage["input_tokens"].as_u64()?,
+        output_tokens: usage["output_tokens"].as_u64()?,
+    })
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn name(&self) -> &str {
+        PROVIDER_ANTHROPIC
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn request_body(&self, messages: &[Message], stream: bool) -> Result<Value> {
+        self.body(messages, stream, MAX_OUTPUT_TOKENS)
+    }
+
+    fn request_body_with_limit(
+        &self,
+        messages: &[Message],
+        stream: bool,
+        max_output_tokens: u32,
+    ) -> Result<Value> {
+        self.body(messages, stream, max_output_tokens)
+    }
+
+    fn generate(&self, messages: &[Message]) -> Result<Completion> {
+        self.generate_with_limit(messages, MAX_OUTPUT_TOKENS)
+    }
+
+    fn generate_with_limit(
+        &self,
+        messages: &[Message],
+        max_output_tokens: u32,
+    ) -> Result<Completion> {
+        let response = self.send(&self.body(messages, false, max_output_tokens)?)?;
+
+        let response_json: Value = response.json().map_err(body_error)?;
+        response_json["content"][0]["text"]
+            .as_str()
+            .map(|text| Completion::new(text).with_usage(usage(&response_json["usage"])))
+            .ok_or_else(|| Error::ProviderResponse(response_json.to_string()))
+    }
+
+    fn generate_stream(
+        &self,
+        messages: &[Message],
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<Completion> {
+        let response = self.send(&self.request_body(messages, true)?)?;
+
+        let mut text = String::new();
+        let mut input_tokens = None;
+        let mut output_tokens = None;
+        read_sse_stream(BufReader::new(response), |data| {
+            let event: Value =
+                serde_json::from_str(data).map_err(|e| Error::ProviderResponse(e.to_string()))?;
+            match event["type"].as_str() {
+                Some("content_block_delta") => {
+                    if let Some(token) = event["delta"]["text"].as_str() {
+                        on_token(token);
+                        text.push_str(token);
+                    }
+                    Ok(())
+                }
+                // input tokens arrive with the first event, the running output count with the last
+                Some("message_start") => {
+                    input_tokens = event["message"]["usage"]["input_tokens"].as_u64();
+                    Ok(())
+                }
+                Some("message_delta") => {
+                    output_tokens = event["usage"]["output_tokens"].as_u64();
+                    Ok(())
+                }
+                Some("error") => Err(Error::ProviderResponse(
+                    error_message(&event).unwrap_or_else(|| data.to_string()),
+                )),
+                _ => Ok(()),
+            }
+        })?;
+
+        let usage = match (input_tokens, output_tokens) {
+            (Some(input_tokens), Some(output_tokens)) => Some(TokenUsage {
+                input_tokens,
+                output_tokens,
+            }),
+            _ => None,
+        };
+        Ok(Completion::new(&text).with_usage(usage))
+    }
+}
@@ -0,0 +1,205 @@
+use reqwest::blocking::Response;
+use serde_json::{json, Value};
+use std::io::BufReader;
+
+use super::http::{body_error, HttpClient};
+use super::sse::read_sse_stream;
+use super::{Completion, LlmProvider, Message, TokenUsage, MAX_OUTPUT_TOKENS, PROVIDER_GEMINI};
+use crate::config::{
+    get_config_key, ConfigPaths, VALID_GEMINI_API_KEY, VALID_GEMINI_MODEL, VALID_GEMINI_URL,
+};
+use crate::credentials::api_key;
+use crate::error::{Error, Result};
+
+pub struct GeminiProvider {
+    api_key: String,
+    url: String,
+    model: String,
+    http: HttpClient,
+}
+
+impl GeminiProvider {
+    pub fn new(api_key: &str, url: &str, model: &str) -> Self {
+        GeminiProvider {
+            api_key: api_key.to_string(),
+            url: url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            http: HttpClient::default(),
+        }
+    }
+
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    pub fn from_config(config: &ConfigPaths) -> Result<Self> {
+        let api_key = api_key(PROVIDER_GEMINI, VALID_GEMINI_API_KEY, config)?;
+        if api_key.is_empty() {
+            return Err(Error::MissingCredentials(
+                "Gemini API key or URL is empty".to_string(),
+            ));
+        }
+        GeminiProvider::from_config_with_key(&api_key, config)
+    }
+
+    pub fn from_config_with_key(api_key: &str, config: &ConfigPaths) -> Result<Self> {
+        let keys = [VALID_GEMINI_URL, VALID_GEMINI_MODEL];
+        let values = get_config_key(&keys, config)?;
+
+        if values[0].is_empty() {
+            return Err(Error::MissingCredentials(
+                "Gemini API key or URL is empty".to_string(),
+            ));
+        }
+
+        Ok(GeminiProvider::new(api_key, &values[0], &values[1])
+            .with_http_client(HttpClient::from_config(config)?))
+    }
+
+    // streaming only changes the endpoint, not the body
+    fn body(&self, messages: &[Message], _stream: bool, max_output_tokens: u32) -> Result<Value> {
+        let system: Vec<Value> = messages
+            .iter()
+            .filter(|message| message.role == "system")
+            .map(|message| json!({ "text": message.content }))
+            .collect();
+        let contents: Vec<Value> = messages
+            .iter()
+            .filter(|message| message.role != "system")
+            .map(|message| {
+                // Gemini names the assistant role "model"
+                let role = if message.role == "assistant" {
+                    "model"
+                } else {
+                    "user"
+                };
+                json!({ "role": role, "parts": [{ "text": message.content }] })
+            })
+            .collect();
+        Ok(json!({
+            "system_instruction": { "parts": system },
+            "contents": contents,
+            "generationConfig": { "maxOutputTokens": max_output_tokens }
+        }))
+    }
+
+    fn send(&self, method: &str, body: &Value) -> Result<Response> {
+        let response = self.http.send(|client| {
+            client
+                .post(format!(
+                    "{}/v1beta/models/{}:{}",
+                    self.url, self.model, method
+                ))
+                .header("x-goog-api-key", &self.api_key)
+                .json(body)
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().unwrap_or_default();
+            // error bodies look like {"error": {"code": 400, "message": ..., "status": ...}}
+            let body = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|error_json| {
+                    error_json["error"]["message"]
+                        .as_str()
+                        .map(|message| message.to_string())
+                })
+                .unwrap_or(body);
+            return Err(Error::ProviderStatus {
+                status: status.as_u16(),
+                body,
+            });
+        }
+        Ok(response)
+    }
+}
+
+fn candidate_text(response_json: &Value) -> Option<&str> {
+    response_json["candidates"][0]["content"]["parts"][0]["text"].as_str()
+}
+
+fn usage(response_json: &Value) -> Option<TokenUsage> {
+    let usage = &response_json["usageMetadata"];
+    Some(TokenUsage {
+        input_tokens: usage["promptTokenCount"].as_u64()?,
+        output_tokens: usage["candidatesTokenCount"].as_u64()?,
+    })
+}
+
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &str {
+        PROVIDER_GEMINI
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn request_body(&self, messages: &[Message], stream: bool) -> Result<Value> {
+        self.body(messages, stream, MAX_OUTPUT_TOKENS)
+    }
+
+    fn request_body_with_limit(
+        &self,
+        messages: &[Message],
+        stream: bool,
+        max_output_tokens: u32,
+    ) -> Result<Value> {
+        self.body(messages, stream, max_output_tokens)
+    }
+
+    fn generate(&self, messages: &[Message]) -> Result<Completion> {
+        self.generate_with_limit(messages, MAX_OUTPUT_TOKENS)
+    }
+
+    fn generate_with_limit(
+        &self,
+        messages: &[Message],
+        max_output_tokens: u32,
+    ) -> Result<Completion> {
+        let response = self.send(
+            "generateContent",
+            &self.body(messages, false, max_output_tokens)?,
+        )?;
+
+        let response_json: Value = response.json().map_err(body_error)?;
+        candidate_text(&response_json)
+            .map(|text| Completion::new(text.trim()).with_usage(usage(&response_json)))
+            .ok_or_else(|| Error::ProviderResponse(response_json.to_string()))
+    }
+
+    fn generate_stream(
+        &self,
+        messages: &[Message],
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<Completion> {
+        let response = self.send(
+            "streamGenerateContent?alt=sse",
+            &self.request_body(messages, true)?,
+        )?;
+
+        let mut text = String::new();
+        let mut last_usage = None;
+        read_sse_stream(BufReader::new(response), |data| {
+            let chunk: Value =
+                serde_json::from_str(data).map_err(|e| Error::ProviderResponse(e.to_string()))?;
+            if let Some(token) = candidate_text(&chunk) {
+                on_token(token);
+                text.push_str(token);
+            }
+            // every chunk reports the running totals
+            if let Some(usage) = usage(&chunk) {
+                last_usage = Some(usage);
+            }
+            Ok(())
+        })?;
+        Ok(Completion::new(text.trim()).with_usage(last_usage))
+    }
+}
@@ -0,0 +1,133 @@
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use super::retry::{is_retryable_status, parse_retry_after, RetryPolicy};
+use crate::config::{
+    get_config_key, ConfigPaths, VALID_CONNECT_TIMEOUT_SECS, VALID_REQUEST_TIMEOUT_SECS,
+};
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeouts {
+    pub request: Duration,
+    pub connect: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            request: Duration::from_secs(60),
+            connect: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn from_config(config: &ConfigPaths) -> Result<Self> {
+        let keys = [VALID_REQUEST_TIMEOUT_SECS, VALID_CONNECT_TIMEOUT_SECS];
+        let values = get_config_key(&keys, config)?;
+
+        let mut seconds = Vec::new();
+        for (key, value) in keys.iter().zip(values.iter()) {
+            match value.parse::<u64>() {
+                Ok(secs) if secs > 0 => seconds.push(secs),
+                _ => {
+                    return Err(Error::invalid_value(
+                        key,
+                        format!("expected an integer > 0, got {}", value),
+                    ))
+                }
+            }
+        }
+
+        Ok(Timeouts {
+            request: Duration::from_secs(seconds[0]),
+            connect: Duration::from_secs(seconds[1]),
+        })
+    }
+}
+
+pub struct HttpClient {
+    client: Client,
+    retry: RetryPolicy,
+}
+
+// Building a client only fails when the TLS backend cannot be initialized, which leaves no
+// provider usable either; the providers' constructors rely on this instead of returning a
+// Result, `from_config` reports the error
+impl Default for HttpClient {
+    fn default() -> Self {
+        HttpClient::new(RetryPolicy::default()).expect("Error building HTTP client")
+    }
+}
+
+impl HttpClient {
+    pub fn new(retry: RetryPolicy) -> Result<Self> {
+        HttpClient::with_timeouts(retry, Timeouts::default())
+    }
+
+    pub fn with_timeouts(retry: RetryPolicy, timeouts: Timeouts) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(timeouts.request)
+            .connect_timeout(timeouts.connect)
+            .build()
+            .map_err(|e| Error::Http(format!("could not build the HTTP client: {}", e)))?;
+        Ok(HttpClient { client, retry })
+    }
+
+    pub fn from_config(config: &ConfigPaths) -> Result<Self> {
+        HttpClient::with_timeouts(
+            RetryPolicy::from_config(config)?,
+            Timeouts::from_config(config)?,
+        )
+    }
+
+    // Sends the request built by `build`, rebuilding it for every retry. Responses with a
+    // non-retryable status are returned as-is so providers can decode their error bodies.
+    pub fn send(&self, build: impl Fn(&Client) -> RequestBuilder) -> Result<Response> {
+        let mut attempt = 1;
+        loop {
+            let last_attempt = attempt >= self.retry.max_attempts;
+            match build(&self.client).send() {
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    if last_attempt || !is_retryable_status(status) {
+                        return Ok(response);
+                    }
+                    let retry_after = response
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(parse_retry_after);
+                    thread::sleep(self.retry.delay(attempt, retry_after));
+                }
+                // a refused connection never reached the server, so resending is safe
+                Err(e) if e.is_connect() && !e.is_timeout() && !last_attempt => {
+                    thread::sleep(self.retry.delay(attempt, None));
+                }
+                Err(e) if e.is_timeout() => return Err(Error::Timeout(e.to_string())),
+                Err(e) => return Err(Error::Http(e.to_string())),
+            }
+            attempt += 1;
+        }
+    }
+}
+
+// Reading the body counts against the request timeout as well
+pub fn body_error(e: reqwest::Error) -> Error {
+    if e.is_timeout() {
+        Error::Timeout(e.to_string())
+    } else {
+        Error::ProviderResponse(e.to_string())
+    }
+}
+
+pub fn stream_error(e: io::Error) -> Error {
+    if e.kind() == io::ErrorKind::TimedOut {
+        Error::Timeout(e.to_string())
+    } else {
+        Error::Http(e.to_string())
+    }
+}
@@ -1 +1,477 @@
-pub mod openai;
\ No newline at end of file
+pub mod anthropic;
+pub mod gemini;
+pub mod http;
+pub mod ollama;
+pub mod openai;
+pub mod retry;
+pub mod sse;
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::config::{
+    get_config_key, get_language, split_list, ConfigPaths, VALID_COMMIT_SCOPES, VALID_COMMIT_TYPES,
+    VALID_FALLBACK_PROVIDERS, VALID_PROMPT_TEMPLATE, VALID_PROVIDER, VALID_STREAM,
+    VALID_USER_LANGUAGE,
+};
+use crate::error::{Error, Result};
+
+pub const PROVIDER_OPENAI: &str = "openai";
+pub const PROVIDER_ANTHROPIC: &str = "anthropic";
+pub const PROVIDER_OLLAMA: &str = "ollama";
+pub const PROVIDER_AZURE: &str = "azure";
+pub const PROVIDER_GEMINI: &str = "gemini";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+impl Message {
+    pub fn system(content: &str) -> Self {
+        Message {
+            role: "system".to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    pub fn user(content: &str) -> Self {
+        Message {
+            role: "user".to_string(),
+            content: content.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn total(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+impl std::ops::Add for TokenUsage {
+    type Output = TokenUsage;
+
+    fn add(self, other: TokenUsage) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input_tokens + other.input_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+        }
+    }
+}
+
+// What a single provider call produced; usage is None when the API does not report it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub text: String,
+    pub usage: Option<TokenUsage>,
+}
+
+impl Completion {
+    pub fn new(text: &str) -> Self {
+        Completion {
+            text: text.to_string(),
+            usage: None,
+        }
+    }
+
+    pub fn with_usage(mut self, usage: Option<TokenUsage>) -> Self {
+        self.usage = usage;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct ProviderFailure {
+    pub provider: String,
+    pub error: Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderSpec {
+    pub name: String,
+    pub model: Option<String>,
+}
+
+impl ProviderSpec {
+    // `provider` or `provider:model`
+    pub fn parse(entry: &str) -> Self {
+        match entry.trim().split_once(':') {
+            Some((name, model)) => ProviderSpec {
+                name: name.trim().to_string(),
+                model: Some(model.trim().to_string()),
+            },
+            None => ProviderSpec {
+                name: entry.trim().to_string(),
+                model: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for ProviderSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.model {
+            Some(model) => write!(f, "{}:{}", self.name, model),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GeneratedMessage {
+    pub text: String,
+    pub provider: String,
+    pub model: String,
+    pub usage: Option<TokenUsage>,
+    // wall time from the first request until the message was complete, including fallbacks
+    pub latency: Duration,
+    pub failures: Vec<ProviderFailure>,
+    // secrets masked in the diff before it was sent, by detector
+    pub redacted: BTreeMap<String, usize>,
+}
+
+// Room for the commit message itself, a subject line and perhaps a short body
+pub const MAX_OUTPUT_TOKENS: u32 = 60;
+
+// Send + Sync so chunk summaries can be requested from several threads at once
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn model(&self) -> &str;
+    fn generate(&self, messages: &[Message]) -> Result<Completion>;
+
+    // `generate` with the reply capped at `max_output_tokens` instead of MAX_OUTPUT_TOKENS, for
+    // requests such as chunk summaries that need more room. Providers without a cap ignore it
+    fn generate_with_limit(
+        &self,
+        messages: &[Message],
+        _max_output_tokens: u32,
+    ) -> Result<Completion> {
+        self.generate(messages)
+    }
+
+    // The JSON body `generate` or `generate_stream` would post, used by --dry-run
+    fn request_body(&self, messages: &[Message], stream: bool) -> Result<Value> {
+        let messages: Vec<Value> = messages
+            .iter()
+            .map(|message| json!({ "role": message.role, "content": message.content }))
+            .collect();
+        Ok(json!({ "model": self.model(), "messages": messages, "stream": stream }))
+    }
+
+    // `request_body` as `generate_with_limit` would post it
+    fn request_body_with_limit(
+        &self,
+        messages: &[Message],
+        stream: bool,
+        _max_output_tokens: u32,
+    ) -> Result<Value> {
+        self.request_body(messages, stream)
+    }
+
+    // Providers without a streaming endpoint hand over the whole message as a single token
+    fn generate_stream(
+        &self,
+        messages: &[Message],
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<Completion> {
+        let completion = self.generate(messages)?;
+        on_token(&completion.text);
+        Ok(completion)
+    }
+}
+
+pub fn build_messages(
+    base_prompt: &str,
+    diff_content: &str,
+    commit_history: &[String],
+) -> Vec<Message> {
+    // build user message with recent commit messages
+    let mut user_message = String::new();
+
+    if !commit_history.is_empty() {
+        user_message.push_str("Recent commit messages for reference:\n");
+        for (i, commit) in commit_history.iter().enumerate() {
+            user_message.push_str(&format!("{}. {}\n", i + 1, commit));
+        }
+        user_message.push_str("\n---\n\n");
+    }
+
+    user_message.push_str("Git diff content:\n");
+    user_message.push_str(diff_content);
+
+    vec![Message::system(base_prompt), Message::user(&user_message)]
+}
+
+pub fn create_provider(name: &str, config: &ConfigPaths) -> Result<Box<dyn LlmProvider>> {
+    create_provider_with_model(name, None, config)
+}
+
+pub fn create_provider_with_model(
+    name: &str,
+    model: Option<&str>,
+    config: &ConfigPaths,
+) -> Result<Box<dyn LlmProvider>> {
+    match (name, model) {
+        (PROVIDER_OPENAI, None) => Ok(Box::new(openai::OpenAiProvider::from_config(config)?)),
+        (PROVIDER_OPENAI, Some(model)) => Ok(Box::new(
+            openai::OpenAiProvider::from_config(config)?.with_model(model),
+        )),
+        (PROVIDER_ANTHROPIC, None) => {
+            Ok(Box::new(anthropic::AnthropicProvider::from_config(config)?))
+        }
+        (PROVIDER_ANTHROPIC, Some(model)) => Ok(Box::new(
+            anthropic::AnthropicProvider::from_config(config)?.with_model(model),
+        )),
+        (PROVIDER_OLLAMA, None) => Ok(Box::new(ollama::OllamaProvider::from_config(config)?)),
+        (PROVIDER_OLLAMA, Some(model)) => Ok(Box::new(
+            ollama::OllamaProvider::from_config(config)?.with_model(model),
+        )),
+        (PROVIDER_AZURE, None) => Ok(Box::new(openai::OpenAiProvider::from_azure_config(config)?)),
+        (PROVIDER_AZURE, Some(deployment)) => Ok(Box::new(
+            openai::OpenAiProvider::from_azure_config(config)?.with_model(deployment),
+        )),
+        (PROVIDER_GEMINI, None) => Ok(Box::new(gemini::GeminiProvider::from_config(config)?)),
+        (PROVIDER_GEMINI, Some(model)) => Ok(Box::new(
+            gemini::GeminiProvider::from_config(config)?.with_model(model),
+        )),
+        _ => Err(Error::invalid_value(VALID_PROVIDER, name)),
+    }
+}
+
+// The configured provider with an empty API key, enough to build the request --dry-run shows.
+// The providers' `from_config_with_key` read everything but the key from the config, so
+// neither api_key_command nor api_key_file is touched
+pub fn keyless_provider_from_config(config: &ConfigPaths) -> Result<Box<dyn LlmProvider>> {
+    let values = get_config_key(&[VALID_PROVIDER], config)?;
+    match values[0].as_str() {
+        PROVIDER_OPENAI => Ok(Box::new(openai::OpenAiProvider::from_config_with_key(
+            "", config,
+        )?)),
+        PROVIDER_ANTHROPIC => Ok(Box::new(
+            anthropic::AnthropicProvider::from_config_with_key("", config)?,
+        )),
+        PROVIDER_OLLAMA => Ok(Box::new(ollama::OllamaProvider::from_config(config)?)),
+        PROVIDER_AZURE => Ok(Box::new(
+            openai::OpenAiProvider::from_azure_config_with_key("", config)?,
+        )),
+        PROVIDER_GEMINI => Ok(Box::new(gemini::GeminiProvider::from_config_with_key(
+            "", config,
+        )?)),
+        name => Err(Error::invalid_value(VALID_PROVIDER, name)),
+    }
+}
+
+pub fn provider_from_config(config: &ConfigPaths) -> Result<Box<dyn LlmProvider>> {
+    let values = get_config_key(&[VALID_PROVIDER], config)?;
+    create_provider(&values[0], config)
+}
+
+// The configured provider followed by the fallback providers, in order
+pub fn provider_chain_from_config(config: &ConfigPaths) -> Result<Vec<ProviderSpec>> {
+    let values = get_config_key(&[VALID_PROVIDER, VALID_FALLBACK_PROVIDERS], config)?;
+
+    let mut chain = vec![ProviderSpec::parse(&values[0])];
+    chain.extend(
+        values[1]
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(ProviderSpec::parse),
+    );
+    Ok(chain)
+}
+
+pub fn generate_commit_message(
+    diff_content: &str,
+    commit_history: &[String],
+    config: &ConfigPaths,
+) -> Result<GeneratedMessage> {
+    let messages = messages_from_config(diff_content, commit_history, config)?;
+    generate_messages(&messages, MAX_OUTPUT_TOKENS, config)
+}
+
+// Sends prepared messages through the configured provider chain, the reply capped at
+// `max_output_tokens`
+pub fn generate_messages(
+    messages: &[Message],
+    max_output_tokens: u32,
+    config: &ConfigPaths,
+) -> Result<GeneratedMessage> {
+    generate_with_fallback(
+        config,
+        |provider| provider.generate_with_limit(messages, max_output_tokens),
+        || true,
+    )
+}
+
+pub fn generate_with_provider(
+    provider: &dyn LlmProvider,
+    diff_content: &str,
+    commit_history: &[String],
+    config: &ConfigPaths,
+) -> Result<GeneratedMessage> {
+    let messages = messages_from_config(diff_content, commit_history, config)?;
+    generate_messages_with_provider(provider, &messages, MAX_OUTPUT_TOKENS)
+}
+
+pub fn generate_messages_with_provider(
+    provider: &dyn LlmProvider,
+    messages: &[Message],
+    max_output_tokens: u32,
+) -> Result<GeneratedMessage> {
+    let start = Instant::now();
+    let completion = provider.generate_with_limit(messages, max_output_tokens)?;
+    Ok(generated_message(provider, completion, start, Vec::new()))
+}
+
+pub fn stream_commit_message(
+    diff_content: &str,
+    commit_history: &[String],
+    config: &ConfigPaths,
+    on_token: &mut dyn FnMut(&str),
+) -> Result<GeneratedMessage> {
+    let messages = messages_from_config(diff_content, commit_history, config)?;
+    // once tokens reached the terminal another provider would print a second message
+    let streamed = Cell::new(false);
+    generate_with_fallback(
+        config,
+        |provider| {
+            provider.generate_stream(&messages, &mut |token| {
+                streamed.set(true);
+                on_token(token);
+            })
+        },
+        || !streamed.get(),
+    )
+}
+
+pub fn stream_with_provider(
+    provider: &dyn LlmProvider,
+    diff_content: &str,
+    commit_history: &[String],
+    config: &ConfigPaths,
+    on_token: &mut dyn FnMut(&str),
+) -> Result<GeneratedMessage> {
+    let messages = messages_from_config(diff_content, commit_history, config)?;
+    let start = Instant::now();
+    let completion = provider.generate_stream(&messages, on_token)?;
+    Ok(generated_message(provider, completion, start, Vec::new()))
+}
+
+fn generate_with_fallback(
+    config: &ConfigPaths,
+    mut generate: impl FnMut(&dyn LlmProvider) -> Result<Completion>,
+    can_fall_back: impl Fn() -> bool,
+) -> Result<GeneratedMessage> {
+    let chain = provider_chain_from_config(config)?;
+    let start = Instant::now();
+    let mut failures = Vec::new();
+
+    for (i, spec) in chain.iter().enumerate() {
+        let result = create_provider_with_model(&spec.name, spec.model.as_deref(), config)
+            .and_then(|provider| Ok((generate(provider.as_ref())?, provider)));
+
+        match result {
+            Ok((completion, provider)) => {
+                return Ok(generated_message(
+                    provider.as_ref(),
+                    completion,
+                    start,
+                    failures,
+                ))
+            }
+            Err(error) if i + 1 < chain.len() && error.should_fall_back() && can_fall_back() => {
+                failures.push(ProviderFailure {
+                    provider: spec.to_string(),
+                    error,
+                });
+            }
+            Err(error) if failures.is_empty() => return Err(error),
+            Err(error) => {
+                failures.push(ProviderFailure {
+                    provider: spec.to_string(),
+                    error,
+                });
+                return Err(Error::AllProvidersFailed(failures));
+            }
+        }
+    }
+
+    Err(Error::AllProvidersFailed(failures))
+}
+
+fn generated_message(
+    provider: &dyn LlmProvider,
+    completion: Completion,
+    start: Instant,
+    failures: Vec<ProviderFailure>,
+) -> GeneratedMessage {
+    GeneratedMessage {
+        text: completion.text,
+        provider: provider.name().to_string(),
+        model: provider.model().to_string(),
+        usage: completion.usage,
+        latency: start.elapsed(),
+        failures,
+        redacted: BTreeMap::new(),
+    }
+}
+
+pub fn streaming_enabled(config: &ConfigPaths) -> bool {
+    matches!(
+        get_config_key(&[VALID_STREAM], config).as_deref(),
+        Ok([value]) if value == "true"
+    )
+}
+
+// The system prompt, prompt_template or prompt.toml in the configured language plus the team's
+// commit conventions, then the history and diff
+pub fn messages_from_config(
+    diff_content: &str,
+    commit_history: &[String],
+    config: &ConfigPaths,
+) -> Result<Vec<Message>> {
+    let prompt = config.dir.join("prompt.toml");
+    let values = get_config_key(
+        &[
+            VALID_USER_LANGUAGE,
+            VALID_PROMPT_TEMPLATE,
+            VALID_COMMIT_TYPES,
+            VALID_COMMIT_SCOPES,
+        ],
+        config,
+    )?;
+    let mut base_prompt = if values[1].is_empty() {
+        get_language(&values[0], prompt)?
+    } else {
+        values[1].clone()
+    };
+
+    let types = split_list(&values[2]);
+    if !types.is_empty() {
+        base_prompt.push_str(&format!(
+            "\n\nOnly use one of these commit types: {}.",
+            types.join(", ")
+        ));
+    }
+    let scopes = split_list(&values[3]);
+    if !scopes.is_empty() {
+        base_prompt.push_str(&format!(
+            "\n\nWhen a scope fits, write it as type(scope) using one of: {}.",
+            scopes.join(", ")
+        ));
+    }
+
+    Ok(build_messages(&base_prompt, diff_content, commit_history))
+}
@@ -0,0 +1,239 @@
+use reqwest::blocking::Response;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader};
+
+use super::http::{body_error, stream_error, HttpClient};
+use super::{Completion, LlmProvider, Message, TokenUsage, MAX_OUTPUT_TOKENS, PROVIDER_OLLAMA};
+use crate::config::{
+    get_config_key, ConfigPaths, VALID_OLLAMA_API, VALID_OLLAMA_MODEL, VALID_OLLAMA_URL,
+};
+use crate::error::{Error, Result};
+
+pub const OLLAMA_API_CHAT: &str = "chat";
+pub const OLLAMA_API_GENERATE: &str = "generate";
+
+pub struct OllamaProvider {
+    url: String,
+    model: String,
+    http: HttpClient,
+    api: String,
+}
+
+impl OllamaProvider {
+    pub fn new(url: &str, model: &str, api: &str) -> Self {
+        OllamaProvider {
+            url: url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            http: HttpClient::default(),
+            api: api.to_string(),
+        }
+    }
+
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    pub fn from_config(config: &ConfigPaths) -> Result<Self> {
+        let keys = [VALID_OLLAMA_URL, VALID_OLLAMA_MODEL, VALID_OLLAMA_API];
+        let values = get_config_key(&keys, config)?;
+
+        if values[0].is_empty() || values[1].is_empty() {
+            return Err(Error::MissingCredentials(
+                "Ollama URL or model is empty".to_string(),
+            ));
+        }
+
+        Ok(OllamaProvider::new(&values[0], &values[1], &values[2])
+            .with_http_client(HttpClient::from_config(config)?))
+    }
+
+    fn body(&self, messages: &[Message], stream: bool, max_output_tokens: u32) -> Result<Value> {
+        match self.api.as_str() {
+            OLLAMA_API_CHAT => {
+                let messages: Vec<Value> = messages
+                    .iter()
+                    .map(|message| json!({ "role": message.role, "content": message.content }))
+                    .collect();
+                Ok(json!({
+                    "model": self.model,
+                    "messages": messages,
+                    "stream": stream,
+                    "options": { "num_predict": max_output_tokens }
+                }))
+            }
+            OLLAMA_API_GENERATE => Ok(json!({
+                "model": self.model,
+                "system": join_role(messages, "system"),
+                "prompt": join_role(messages, "user"),
+                "stream": stream,
+                "options": { "num_predict": max_output_tokens }
+            })),
+            _ => Err(Error::invalid_value(
+                VALID_OLLAMA_API,
+                format!("unknown Ollama API {}", self.api),
+            )),
+        }
+    }
+
+    fn send(&self, body: &Value) -> Result<Response> {
+        let response = self.http.send(|client| {
+            client
+                .post(format!("{}/api/{}", self.url, self.api))
+                .json(body)
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().unwrap_or_default();
+            // error bodies look like {"error": "model 'llama3' not found"}
+            let body = serde_json::from_str::<Value>(&text)
+                .ok()
+                .and_then(|error_json| error_json["error"].as_str().map(|e| e.to_string()))
+                .unwrap_or(text);
+            return Err(Error::ProviderStatus {
+                status: status.as_u16(),
+                body,
+            });
+        }
+        Ok(response)
+    }
+}
+
+fn join_role(messages: &[Message], role: &str) -> String {
+    messages
+        .iter()
+        .filter(|message| message.role == role)
+        .map(|message| message.content.as_str())
+        .collect::<Vec<&str>>()
+        .join("\n\n")
+}
+
+struct Chunk {
+    content: Option<String>,
+    usage: Option<TokenUsage>,
+}
+
+// Parses one NDJSON line; the closing `done` line carries the token counts but no text
+fn parse_chunk(line: &str) -> Result<Chunk> {
+    let chunk: Value =
+        serde_json::from_str(line).map_err(|e| Error::ProviderResponse(e.to_string()))?;
+    if let Some(error) = chunk["error"].as_str() {
+        return Err(Error::ProviderResponse(error.to_string()));
+    }
+    let content = chunk["message"]["content"]
+        .as_str()
+        .or_else(|| chunk["response"].as_str());
+    let done = chunk["done"].as_bool() == Some(true);
+    if content.is_none() && !done {
+        return Err(Error::ProviderResponse(line.to_string()));
+    }
+
+    let usage = match (
+        chunk["prompt_eval_count"].as_u64(),
+        chunk["eval_count"].as_u64(),
+    ) {
+        (Some(input_tokens), Some(output_tokens)) if done => Some(TokenUsage {
+            input_tokens,
+            output_tokens,
+        }),
+        _ => None,
+    };
+    Ok(Chunk {
+        content: content.map(|content| content.to_string()),
+        usage,
+    })
+}
+
+// Ollama answers with one JSON object per line; a non-streamed response is a single line
+pub fn parse_ndjson_response(body: &str) -> Result<Completion> {
+    let mut completion = Completion::new("");
+    let mut parsed_any = false;
+
+    for line in body.lines().filter(|line| !line.trim().is_empty()) {
+        let chunk = parse_chunk(line)?;
+        if let Some(content) = chunk.content {
+            completion.text.push_str(&content);
+            parsed_any = true;
+        }
+        if chunk.usage.is_some() {
+            completion.usage = chunk.usage;
+        }
+    }
+
+    if parsed_any {
+        Ok(completion)
+    } else {
+        Err(Error::ProviderResponse(body.to_string()))
+    }
+}
+
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &str {
+        PROVIDER_OLLAMA
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn request_body(&self, messages: &[Message], stream: bool) -> Result<Value> {
+        self.body(messages, stream, MAX_OUTPUT_TOKENS)
+    }
+
+    fn request_body_with_limit(
+        &self,
+        messages: &[Message],
+        stream: bool,
+        max_output_tokens: u32,
+    ) -> Result<Value> {
+        self.body(messages, stream, max_output_tokens)
+    }
+
+    fn generate(&self, messages: &[Message]) -> Result<Completion> {
+        self.generate_with_limit(messages, MAX_OUTPUT_TOKENS)
+    }
+
+    fn generate_with_limit(
+        &self,
+        messages: &[Message],
+        max_output_tokens: u32,
+    ) -> Result<Completion> {
+        let response = self.send(&self.body(messages, false, max_output_tokens)?)?;
+        let text = response.text().map_err(body_error)?;
+
+        let completion = parse_ndjson_response(&text)?;
+        Ok(Completion::new(completion.text.trim()).with_usage(completion.usage))
+    }
+
+    fn generate_stream(
+        &self,
+        messages: &[Message],
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<Completion> {
+        let response = self.send(&self.request_body(messages, true)?)?;
+
+        let mut text = String::new();
+        let mut usage = None;
+        for line in BufReader::new(response).lines() {
+            let line = line.map_err(stream_error)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let chunk = parse_chunk(&line)?;
+            if let Some(token) = chunk.content {
+                on_token(&token);
+                text.push_str(&token);
+            }
+            if chunk.usage.is_some() {
+                usage = chunk.usage;
+            }
+        }
+        Ok(Completion::new(text.trim()).with_usage(usage))
+    }
+}
@@ -1,94 +1,261 @@
-use reqwest::blocking::Client;
+use reqwest::blocking::Response;
 use serde_json::{json, Value};
-use std::io::{Error, ErrorKind, Result};
+use std::io::BufReader;
 
+use super::http::{body_error, HttpClient};
+use super::sse::read_sse_stream;
+use super::{
+    generate_with_provider, Completion, GeneratedMessage, LlmProvider, Message, TokenUsage,
+    MAX_OUTPUT_TOKENS, PROVIDER_AZURE, PROVIDER_OPENAI,
+};
 use crate::config::{
-    get_config_key, get_language, VALID_OPENAI_API_KEY, VALID_OPENAI_MODEL, VALID_OPENAI_URL,
-    VALID_USER_LANGUAGE,
+    get_config_key, ConfigPaths, VALID_AZURE_API_KEY, VALID_AZURE_API_VERSION,
+    VALID_AZURE_DEPLOYMENT, VALID_AZURE_ENDPOINT, VALID_OPENAI_API_KEY, VALID_OPENAI_MODEL,
+    VALID_OPENAI_URL,
 };
+use crate::credentials::api_key;
+use crate::error::{Error, Result};
+
+enum Flavor {
+    OpenAi,
+    Azure { api_version: String },
+}
+
+pub struct OpenAiProvider {
+    api_key: String,
+    url: String,
+    model: String,
+    http: HttpClient,
+    flavor: Flavor,
+}
+
+impl OpenAiProvider {
+    pub fn new(api_key: &str, url: &str, model: &str) -> Self {
+        OpenAiProvider {
+            api_key: api_key.to_string(),
+            url: url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            http: HttpClient::default(),
+            flavor: Flavor::OpenAi,
+        }
+    }
+
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    // Azure routes by deployment name instead of model and authenticates with an api-key header
+    pub fn azure(api_key: &str, url: &str, deployment: &str, api_version: &str) -> Self {
+        OpenAiProvider {
+            api_key: api_key.to_string(),
+            url: url.trim_end_matches('/').to_string(),
+            model: deployment.to_string(),
+            http: HttpClient::default(),
+            flavor: Flavor::Azure {
+                api_version: api_version.to_string(),
+            },
+        }
+    }
+
+    pub fn from_config(config: &ConfigPaths) -> Result<Self> {
+        let api_key = api_key(PROVIDER_OPENAI, VALID_OPENAI_API_KEY, config)?;
+        if api_key.is_empty() {
+            return Err(Error::MissingCredentials(
+                "OpenAI API key or URL is empty".to_string(),
+            ));
+        }
+        OpenAiProvider::from_config_with_key(&api_key, config)
+    }
+
+    pub fn from_config_with_key(api_key: &str, config: &ConfigPaths) -> Result<Self> {
+        let keys = [VALID_OPENAI_URL, VALID_OPENAI_MODEL];
+        let values = get_config_key(&keys, config)?;
+
+        if values[0].is_empty() {
+            return Err(Error::MissingCredentials(
+                "OpenAI API key or URL is empty".to_string(),
+            ));
+        }
+
+        Ok(OpenAiProvider::new(api_key, &values[0], &values[1])
+            .with_http_client(HttpClient::from_config(config)?))
+    }
+
+    pub fn from_azure_config(config: &ConfigPaths) -> Result<Self> {
+        let api_key = api_key(PROVIDER_AZURE, VALID_AZURE_API_KEY, config)?;
+        if api_key.is_empty() {
+            return Err(Error::MissingCredentials(
+                "Azure API key or endpoint is empty".to_string(),
+            ));
+        }
+        OpenAiProvider::from_azure_config_with_key(&api_key, config)
+    }
 
-pub fn openai_request(diff_content: &str, commit_history: &[String], path: &str) -> Result<()> {
-    let keys = [
-        VALID_OPENAI_API_KEY,
-        VALID_OPENAI_URL,
-        VALID_OPENAI_MODEL,
-        VALID_USER_LANGUAGE,
-    ];
-    let mut openai_api_key = String::new();
-    let mut openai_url = String::new();
-    let mut openai_model = String::new();
-    let mut user_language = String::new();
-    let config_dir = format!("{}/config.toml", path);
-    let prompt = format!("{}/prompt.toml", path);
-    match get_config_key(&keys, config_dir) {
-        Ok(values) => {
-            openai_api_key = values[0].clone();
-            openai_url = values[1].clone();
-            openai_model = values[2].clone();
-            user_language = values[3].clone();
+    pub fn from_azure_config_with_key(api_key: &str, config: &ConfigPaths) -> Result<Self> {
+        let keys = [
+            VALID_AZURE_ENDPOINT,
+            VALID_AZURE_DEPLOYMENT,
+            VALID_AZURE_API_VERSION,
+        ];
+        let values = get_config_key(&keys, config)?;
+
+        if values[0].is_empty() {
+            return Err(Error::MissingCredentials(
+                "Azure API key or endpoint is empty".to_string(),
+            ));
         }
-        Err(e) => {
-            eprintln!("Error: {}", e);
+        if values[1].is_empty() || values[2].is_empty() {
+            return Err(Error::MissingCredentials(
+                "Azure deployment or API version is empty".to_string(),
+            ));
         }
+
+        Ok(
+            OpenAiProvider::azure(api_key, &values[0], &values[1], &values[2])
+                .with_http_client(HttpClient::from_config(config)?),
+        )
     }
 
-    if openai_api_key.is_empty() || openai_url.is_empty() {
-        eprintln!("Error: OpenAI API key or URL is empty");
-        return Err(Error::new(
-            ErrorKind::NotFound,
-            "OpenAI API key or URL is empty",
-        ));
+    fn endpoint(&self) -> String {
+        match &self.flavor {
+            Flavor::OpenAi => format!("{}/v1/chat/completions", self.url),
+            Flavor::Azure { api_version } => format!(
+                "{}/openai/deployments/{}/chat/completions?api-version={}",
+                self.url, self.model, api_version
+            ),
+        }
     }
-    let base_prompt = get_language(user_language.as_str(), prompt);
 
-    // build user message with recent commit messages
-    let mut user_message = String::new();
+    fn body(&self, messages: &[Message], stream: bool, max_output_tokens: u32) -> Result<Value> {
+        let messages: Vec<Value> = messages
+            .iter()
+            .map(|message| json!({ "role": message.role, "content": message.content }))
+            .collect();
+        let mut body = json!({
+            "model": self.model,
+            "messages": messages,
+            "max_tokens": max_output_tokens
+        });
+        if stream {
+            body["stream"] = json!(true);
+            if matches!(self.flavor, Flavor::OpenAi) {
+                // the last chunk then carries the token counts, older Azure API versions reject it
+                body["stream_options"] = json!({ "include_usage": true });
+            }
+        }
+        Ok(body)
+    }
+
+    fn send(&self, body: &Value) -> Result<Response> {
+        let response = self.http.send(|client| {
+            let request = match self.flavor {
+                Flavor::OpenAi => client
+                    .post(self.endpoint())
+                    .header("Authorization", format!("Bearer {}", self.api_key)),
+                Flavor::Azure { .. } => client
+                    .post(self.endpoint())
+                    .header("api-key", &self.api_key),
+            };
+            request.json(body)
+        })?;
 
-    if !commit_history.is_empty() {
-        user_message.push_str("Recent commit messages for reference:\n");
-        for (i, commit) in commit_history.iter().enumerate() {
-            user_message.push_str(&format!("{}. {}\n", i + 1, commit));
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::ProviderStatus {
+                status: status.as_u16(),
+                body: response.text().unwrap_or_default(),
+            });
         }
-        user_message.push_str("\n---\n\n");
-    }
-
-    user_message.push_str("Git diff content:\n");
-    user_message.push_str(diff_content);
-
-    let client = Client::new();
-    let response = client
-        .post(format!("{}/v1/chat/completions", openai_url))
-        .header("Authorization", format!("Bearer {}", openai_api_key))
-        .json(&json!({
-            "model": openai_model,
-            "messages": [
-                {
-                    "role": "system",
-                    "content": base_prompt
-                },
-                {
-                    "role": "user",
-                    "content": user_message
-                }
-            ],
-            "max_tokens": 60
-        }))
-        .send()
-        .expect("Error sending request");
-
-    if response.status().is_success() {
-        let response_json: Value = response.json().expect("Error parsing response");
-        if let Some(text) = response_json["choices"][0]["message"]["content"].as_str() {
-            println!("{}", text);
-        } else {
-            eprintln!("Error: Could not parse response");
+        Ok(response)
+    }
+}
+
+fn usage(response_json: &Value) -> Option<TokenUsage> {
+    let usage = &response_json["usage"];
+    Some(TokenUsage {
+        input_tokens: usage["prompt_tokens"].as_u64()?,
+        output_tokens: usage["completion_tokens"].as_u64()?,
+    })
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &str {
+        match self.flavor {
+            Flavor::OpenAi => PROVIDER_OPENAI,
+            Flavor::Azure { .. } => PROVIDER_AZURE,
         }
-    } else {
-        eprintln!(
-            "Error: Request failed with status code: {}",
-            response.status()
-        );
     }
 
-    Ok(())
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn request_body(&self, messages: &[Message], stream: bool) -> Result<Value> {
+        self.body(messages, stream, MAX_OUTPUT_TOKENS)
+    }
+
+    fn request_body_with_limit(
+        &self,
+        messages: &[Message],
+        stream: bool,
+        max_output_tokens: u32,
+    ) -> Result<Value> {
+        self.body(messages, stream, max_output_tokens)
+    }
+
+    fn generate(&self, messages: &[Message]) -> Result<Completion> {
+        self.generate_with_limit(messages, MAX_OUTPUT_TOKENS)
+    }
+
+    fn generate_with_limit(
+        &self,
+        messages: &[Message],
+        max_output_tokens: u32,
+    ) -> Result<Completion> {
+        let response = self.send(&self.body(messages, false, max_output_tokens)?)?;
+
+        let response_json: Value = response.json().map_err(body_error)?;
+        response_json["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|text| Completion::new(text).with_usage(usage(&response_json)))
+            .ok_or_else(|| Error::ProviderResponse(response_json.to_string()))
+    }
+
+    fn generate_stream(
+        &self,
+        messages: &[Message],
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<Completion> {
+        let response = self.send(&self.request_body(messages, true)?)?;
+
+        let mut completion = Completion::new("");
+        read_sse_stream(BufReader::new(response), |data| {
+            let chunk: Value =
+                serde_json::from_str(data).map_err(|e| Error::ProviderResponse(e.to_string()))?;
+            if let Some(token) = chunk["choices"][0]["delta"]["content"].as_str() {
+                on_token(token);
+                completion.text.push_str(token);
+            }
+            if let Some(usage) = usage(&chunk) {
+                completion.usage = Some(usage);
+            }
+            Ok(())
+        })?;
+        Ok(completion)
+    }
+}
+
+pub fn openai_request(
+    diff_content: &str,
+    commit_history: &[String],
+    config: &ConfigPaths,
+) -> Result<GeneratedMessage> {
+    let provider = OpenAiProvider::from_config(config)?;
+    generate_with_provider(&provider, diff_content, commit_history, config)
 }
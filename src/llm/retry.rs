@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use crate::config::{
+    get_config_key, ConfigPaths, VALID_RETRY_BACKOFF_MS, VALID_RETRY_MAX_ATTEMPTS,
+};
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &ConfigPaths) -> Result<Self> {
+        let keys = [VALID_RETRY_MAX_ATTEMPTS, VALID_RETRY_BACKOFF_MS];
+        let values = get_config_key(&keys, config)?;
+
+        let max_attempts = values[0].parse::<u32>().map_err(|_| {
+            Error::invalid_value(keys[0], format!("expected an integer, got {}", values[0]))
+        })?;
+        let backoff_ms = values[1].parse::<u64>().map_err(|_| {
+            Error::invalid_value(keys[1], format!("expected an integer, got {}", values[1]))
+        })?;
+
+        Ok(RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            base_delay: Duration::from_millis(backoff_ms),
+            ..RetryPolicy::default()
+        })
+    }
+
+    // exponential backoff for the given 1-based attempt, unless the server asked for a delay
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let delay = retry_after.unwrap_or_else(|| {
+            self.base_delay
+                .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        });
+        delay.min(self.max_delay)
+    }
+}
+
+// Rate limits and gateway failures are transient; other client errors will fail again
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+// Only the delta-seconds form of Retry-After is supported, HTTP dates fall back to backoff
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
@@ -0,0 +1,24 @@
+use std::io::BufRead;
+
+use super::http::stream_error;
+use crate::error::Result;
+
+// Feeds the payload of every `data:` line to `on_data` until the stream closes or sends [DONE]
+pub fn read_sse_stream<R: BufRead>(
+    reader: R,
+    mut on_data: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    for line in reader.lines() {
+        let line = line.map_err(stream_error)?;
+        let Some(data) = line.strip_prefix("data:") else {
+            // event names, comments and keep-alives carry no payload
+            continue;
+        };
+        let data = data.trim_start();
+        if data == "[DONE]" {
+            break;
+        }
+        on_data(data)?;
+    }
+    Ok(())
+}
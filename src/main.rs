@@ -1,18 +1,52 @@
 extern crate commit_crafter;
-use std::collections::hash_map::DefaultHasher;
-use std::env;
+use std::collections::BTreeMap;
 use std::fs;
-use std::hash::{Hash, Hasher};
-use std::path::Path;
+use std::io::{self, ErrorKind, IsTerminal, Read, Write};
 
-use commit_crafter::{config, git_integration, install, llm, uninstall};
+use commit_crafter::git_integration::{DiffOptions, DiffSource};
+use commit_crafter::{
+    config, git_integration, install, llm, project, redact, uninstall, CommitCrafter, Error, Result,
+};
 
-use clap::{arg, Command};
+use clap::{arg, ArgAction, ArgGroup, Command};
 
 fn main() {
     let matches = Command::new("commit crafter")
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
+        .arg(arg!(-v --verbose "Print which provider generated the message").global(true))
+        .arg(
+            arg!(-c --config <KEY_VALUE> "Override a configuration key for this run, e.g. -c stream=false")
+                .action(ArgAction::Append)
+                .global(true),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Generate a commit message for the staged changes (the default)")
+                .version(env!("CARGO_PKG_VERSION"))
+                .author(env!("CARGO_PKG_AUTHORS"))
+                .arg(arg!(--staged "Describe the staged changes (the default)"))
+                .arg(arg!(--unstaged "Describe the working tree changes that are not staged"))
+                .arg(arg!(--all "Describe all changes to tracked files, staged or not"))
+                .arg(arg!(--commit <REV> "Describe an existing commit"))
+                .arg(arg!(--range <RANGE> "Describe the changes in A..B"))
+                .arg(arg!(--"diff-file" <PATH> "Describe a patch file instead of asking git"))
+                .arg(arg!(--stdin "Read the patch from standard input"))
+                .group(
+                    ArgGroup::new("source")
+                        .args([
+                            "staged",
+                            "unstaged",
+                            "all",
+                            "commit",
+                            "range",
+                            "diff-file",
+                            "stdin",
+                        ])
+                        .multiple(false),
+                )
+                .arg(arg!(--"dry-run" "Print the request body and estimated tokens instead of sending it")),
+        )
         .subcommand(
             Command::new("install")
                 .about("Install the pre-commit hook")
@@ -28,12 +62,18 @@ fn main() {
                     Command::new("set")
                         .about("Set a configuration option")
                         .arg(arg!(<KEY> "The configuration key to set"))
-                        .arg(arg!(<VALUE> "The value to set the key to")),
+                        .arg(arg!(<VALUE> "The value to set the key to"))
+                        .arg(arg!(--global "Set the key for every project instead of this one"))
+                        .arg(
+                            arg!(--repo "Set the key in the committed .commit_crafter.toml shared with the team")
+                                .conflicts_with("global"),
+                        ),
                 )
                 .subcommand(
                     Command::new("get")
                         .about("Get a configuration option")
-                        .arg(arg!(<KEY> "The configuration key to get")),
+                        .arg(arg!(<KEY> "The configuration key to get"))
+                        .arg(arg!(--"show-secret" "Print an API key or other secret in full instead of masked")),
                 )
                 .subcommand(Command::new("list").about("List all configuration options")),
         )
@@ -45,97 +85,172 @@ fn main() {
         )
         .get_matches();
 
+    // every error variant has its own exit status, see Error::exit_code
+    if let Err(e) = run(&matches) {
+        eprintln!("Error: {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run(matches: &clap::ArgMatches) -> Result<()> {
+    let verbose = matches.get_flag("verbose");
+    let overrides = match matches.get_many::<String>("config") {
+        Some(overrides) => config::parse_cli_overrides(&overrides.collect::<Vec<&String>>())?,
+        None => Vec::new(),
+    };
+
     match matches.subcommand() {
-        Some(("install", _sub_matches)) => {
-            let _ = install::install_commit_msg_hook();
-        }
-        Some(("uninstall", _sub_matches)) => {
-            let _ = uninstall::uninstall_commit_msg_hook();
-        }
-        Some(("config", sub_matches)) => {
-            handle_config_subcommand(sub_matches);
-        }
-        _none => match git_integration::run_git_diff() {
-            Ok(output) => {
-                if output.is_empty() {
-                    eprintln!("Error: No changes to commit");
-                    std::process::exit(1);
-                }
-                let config_dir = get_config_dir("");
+        Some(("install", _sub_matches)) => install::install_commit_msg_hook(),
+        Some(("uninstall", _sub_matches)) => uninstall::uninstall_commit_msg_hook(),
+        Some(("config", sub_matches)) => handle_config_subcommand(sub_matches, overrides),
+        Some(("generate", sub_matches)) => generate(
+            diff_source(sub_matches)?,
+            sub_matches.get_flag("dry-run"),
+            verbose,
+            overrides,
+        ),
+        _none => generate(DiffSource::Staged, false, verbose, overrides),
+    }
+}
 
-                // Initialize config if needed
-                if let Err(e) = config::ensure_config_initialized(&config_dir) {
-                    eprintln!("Warning: Failed to initialize config: {}", e);
-                }
+fn diff_source(matches: &clap::ArgMatches) -> Result<DiffSource> {
+    let source = if let Some(rev) = matches.get_one::<String>("commit") {
+        DiffSource::Commit(rev.to_string())
+    } else if let Some(range) = matches.get_one::<String>("range") {
+        DiffSource::Range(range.to_string())
+    } else if let Some(path) = matches.get_one::<String>("diff-file") {
+        let patch = fs::read_to_string(path)
+            .map_err(|e| Error::Io(io::Error::new(e.kind(), format!("{}: {}", path, e))))?;
+        DiffSource::Patch(patch)
+    } else if matches.get_flag("stdin") {
+        let mut patch = String::new();
+        io::stdin().read_to_string(&mut patch)?;
+        DiffSource::Patch(patch)
+    } else if matches.get_flag("unstaged") {
+        DiffSource::Unstaged
+    } else if matches.get_flag("all") {
+        DiffSource::All
+    } else {
+        DiffSource::Staged
+    };
+    Ok(source)
+}
 
-                // get recent 5 commit messages as reference
-                let commit_history = match git_integration::get_recent_commits(5) {
-                    Ok(commits) => commits,
-                    Err(e) => {
-                        eprintln!("Warning: Failed to get recent commits: {}", e);
-                        Vec::new() // if failed to get recent commits, use empty vector
-                    }
-                };
+fn generate(
+    source: DiffSource,
+    dry_run: bool,
+    verbose: bool,
+    overrides: Vec<(String, String)>,
+) -> Result<()> {
+    let paths = project::config_paths()?.overrides(overrides);
 
-                llm::openai::openai_request(&output, &commit_history, &config_dir).unwrap();
-            }
-            Err(e) => eprintln!("Error: {}", e),
-        },
+    // Initialize config if needed
+    if let Err(e) = config::ensure_config_initialized(&paths.dir) {
+        eprintln!("Warning: Failed to initialize config: {}", e);
     }
-}
 
-fn get_config_dir(config_url: &str) -> String {
-    let home_dir = env::var("HOME").expect("Error getting home directory");
-    let base_config_dir = format!("{}/.config/commit_crafter", home_dir);
-
-    // Try to get git root directory for project-specific config
-    match git_integration::get_git_root_dir() {
-        Ok(git_root) => {
-            // Create a hash of the git root path to create a unique config directory for this project
-            let mut hasher = DefaultHasher::new();
-            git_root.hash(&mut hasher);
-            let project_hash = hasher.finish();
-
-            let project_name = git_root
-                .file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("unknown");
-
-            let project_config_dir = format!(
-                "{}/projects/{}-{:x}",
-                base_config_dir, project_name, project_hash
+    let mut options = DiffOptions::load(&paths)?;
+    options.source = source;
+    let output = git_integration::run_git_diff(&options)?;
+    if output.is_empty() {
+        eprintln!("Error: No changes to commit");
+        std::process::exit(1);
+    }
+
+    // get recent 5 commit messages as reference
+    let history = match options.source.history_base() {
+        Some(base) => git_integration::get_recent_commits_from(&base, 5),
+        None => git_integration::get_recent_commits(5),
+    };
+    let commit_history = match history {
+        Ok(commits) => commits,
+        Err(e) => {
+            eprintln!("Warning: Failed to get recent commits: {}", e);
+            Vec::new() // if failed to get recent commits, use empty vector
+        }
+    };
+
+    let crafter = CommitCrafter::with_paths(&paths)
+        .diff(&output)
+        .history(&commit_history);
+
+    // stream tokens only when a person is watching, the hook captures stdout
+    let streaming = io::stdout().is_terminal() && llm::streaming_enabled(&paths);
+
+    if dry_run {
+        // the body of an interactive run, even when piped into jq
+        let request = crafter.dry_run(llm::streaming_enabled(&paths))?;
+        report_redacted(&request.redacted);
+        for body in &request.bodies {
+            let body = serde_json::to_string_pretty(body)
+                .map_err(|e| Error::Io(io::Error::new(ErrorKind::InvalidData, e)))?;
+            println!("{}", body);
+        }
+        eprintln!("Provider: {} ({})", request.provider, request.model);
+        eprintln!("Estimated input tokens: {}", request.estimated_tokens);
+        if request.summary_chunks > 0 {
+            eprintln!(
+                "The diff is over diff_token_budget: these are the {} chunk summary requests a real run sends first, the commit message request is built from their replies",
+                request.summary_chunks
             );
+        }
+        return Ok(());
+    }
 
-            // Create project config directory if it doesn't exist
-            if let Err(e) = fs::create_dir_all(&project_config_dir) {
-                eprintln!("Warning: Failed to create project config directory: {}", e);
-                eprintln!("Falling back to global config directory");
-                // Fall back to global config
-                let global_config_dir = format!("{}/global", base_config_dir);
-                let _ = fs::create_dir_all(&global_config_dir);
-                format!("{}/{}", global_config_dir, config_url)
-            } else {
-                format!("{}/{}", project_config_dir, config_url)
-            }
+    let message = if streaming {
+        let message = crafter.generate_stream(&mut |token| {
+            print!("{}", token);
+            let _ = io::stdout().flush();
+        })?;
+        println!();
+        message
+    } else {
+        let message = crafter.generate()?;
+        println!("{}", message.text);
+        message
+    };
+
+    report_redacted(&message.redacted);
+    if verbose {
+        for failure in &message.failures {
+            eprintln!("Provider {} failed: {}", failure.provider, failure.error);
         }
-        Err(_) => {
-            // Not in a git repository, use global config
-            let global_config_dir = format!("{}/global", base_config_dir);
-            let _ = fs::create_dir_all(&global_config_dir);
-            format!("{}/{}", global_config_dir, config_url)
+        eprintln!(
+            "Generated by {} ({}) in {} ms",
+            message.provider,
+            message.model,
+            message.latency.as_millis()
+        );
+        if let Some(usage) = message.usage {
+            eprintln!(
+                "Tokens: {} input, {} output",
+                usage.input_tokens, usage.output_tokens
+            );
         }
     }
+    Ok(())
+}
+
+// Nothing that looks like a secret left the machine, say what was masked
+fn report_redacted(redacted: &BTreeMap<String, usize>) {
+    let total: usize = redacted.values().sum();
+    if total > 0 {
+        eprintln!(
+            "Redacted {} secrets from the diff: {}",
+            total,
+            redact::describe(redacted)
+        );
+    }
 }
 
-fn handle_config_subcommand(sub_matches: &clap::ArgMatches) {
-    let config_dir = get_config_dir("config.toml");
-    let config_dir_path = Path::new(&config_dir).parent().unwrap();
+fn handle_config_subcommand(
+    sub_matches: &clap::ArgMatches,
+    overrides: Vec<(String, String)>,
+) -> Result<()> {
+    let paths = project::config_paths()?.overrides(overrides);
 
     // Initialize config if needed
-    if let Err(e) = config::ensure_config_initialized(config_dir_path) {
-        eprintln!("Error: Failed to initialize config: {}", e);
-        std::process::exit(1);
-    }
+    config::ensure_config_initialized(&paths.dir)?;
 
     match sub_matches.subcommand() {
         Some(("set", matches)) => {
@@ -145,28 +260,47 @@ fn handle_config_subcommand(sub_matches: &clap::ArgMatches) {
             let value = matches
                 .get_one::<String>("VALUE")
                 .expect("Required VALUE missing");
-            config::set_config_key(key, value, &config_dir).expect("Failed to set configuration");
+            if matches.get_flag("global") {
+                let global_config_dir = project::global_config_dir()?;
+                config::ensure_config_initialized(&global_config_dir)?;
+                config::set_config_key(key, value, global_config_dir.join("config.toml"))?;
+            } else if matches.get_flag("repo") {
+                let repo_config = paths
+                    .repo
+                    .ok_or_else(|| Error::Git("Not in a git repository".to_string()))?;
+                if !repo_config.exists() {
+                    config::write_config_to_toml("", &repo_config)?;
+                }
+                config::set_config_key(key, value, &repo_config)?;
+            } else {
+                config::set_config_key(key, value, paths.config_file())?;
+            }
         }
         Some(("get", matches)) => {
             let key = matches
                 .get_one::<String>("KEY")
                 .expect("Required KEY missing");
-            let value = config::get_config_key(&[key.as_str()], &config_dir)
-                .expect("Failed to get configuration")
-                .join("\n");
-            println!("{}", value);
+            // the value alone on stdout so scripts can keep reading it
+            let values = config::get_config_key_with_source(&[key.as_str()], &paths)?;
+            for (value, source) in values {
+                if matches.get_flag("show-secret") {
+                    println!("{}", value);
+                } else {
+                    println!("{}", config::display_value(key, &value));
+                }
+                eprintln!("(from {})", source);
+            }
         }
         Some(("list", _)) => {
-            let keys = [
-                "openai_api_key",
-                "openai_url",
-                "openai_model",
-                "user_language",
-            ];
-            let values =
-                config::get_config_key(&keys, &config_dir).expect("Failed to list configurations");
-            for (key, value) in keys.iter().zip(values.iter()) {
-                println!("{}: {}", key, value);
+            let keys = config::CONFIG_KEYS;
+            let values = config::get_config_key_with_source(keys, &paths)?;
+            for (key, (value, source)) in keys.iter().zip(values.iter()) {
+                println!(
+                    "{}: {} ({})",
+                    key,
+                    config::display_value(key, value),
+                    source
+                );
             }
         }
         _none => {
@@ -174,4 +308,5 @@ fn handle_config_subcommand(sub_matches: &clap::ArgMatches) {
             std::process::exit(1);
         }
     }
+    Ok(())
 }
@@ -0,0 +1,148 @@
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::{ConfigPaths, REPO_CONFIG_FILE};
+use crate::error::{Error, Result};
+use crate::git_integration;
+
+// Hex digits of the SHA-256 digest kept in a project directory name
+const ID_LENGTH: usize = 16;
+
+// $HOME/.config/commit_crafter
+pub fn base_config_dir() -> Result<PathBuf> {
+    let home_dir = env::var("HOME")
+        .map_err(|e| Error::Io(io::Error::new(ErrorKind::NotFound, format!("HOME: {}", e))))?;
+    Ok(PathBuf::from(home_dir)
+        .join(".config")
+        .join("commit_crafter"))
+}
+
+pub fn global_config_dir() -> Result<PathBuf> {
+    Ok(base_config_dir()?.join("global"))
+}
+
+// The config directory of the current repository, or the global one outside a repository or
+// when the project directory cannot be created
+pub fn config_dir() -> Result<PathBuf> {
+    Ok(config_paths()?.dir)
+}
+
+// The config layers of the current repository, resolved once per call
+pub fn config_paths() -> Result<ConfigPaths> {
+    let base_config_dir = base_config_dir()?;
+    let global_config_dir = base_config_dir.join("global");
+    let global_config_file = global_config_dir.join("config.toml");
+
+    let paths = match git_integration::get_git_root_dir() {
+        Ok(git_root) => {
+            let project_config_dir = project_config_dir(&base_config_dir, &git_root);
+            let config_dir = match fs::create_dir_all(&project_config_dir) {
+                Ok(()) => project_config_dir,
+                Err(e) => {
+                    eprintln!("Warning: Failed to create project config directory: {}", e);
+                    eprintln!("Falling back to global config directory");
+                    global_config_dir
+                }
+            };
+            ConfigPaths::new(config_dir)
+                .global(&global_config_file)
+                .repo(git_root.join(REPO_CONFIG_FILE))
+        }
+        // Not in a git repository, use global config
+        Err(_) => ConfigPaths::new(global_config_dir).global(&global_config_file),
+    };
+    let _ = fs::create_dir_all(&paths.dir);
+    Ok(paths)
+}
+
+// A digest of the root commit, so moving, renaming or re-cloning the repository keeps its
+// config. Without one, in a shallow clone or before the first commit, the origin URL and
+// failing that the root path stand in
+pub fn project_id(git_root: &Path) -> String {
+    let identity = match git_integration::get_root_commit(git_root) {
+        Ok(commit) => format!("commit:{}", commit),
+        Err(_) => fallback_identity(git_root),
+    };
+    digest(&identity)
+}
+
+fn fallback_identity(git_root: &Path) -> String {
+    match git_integration::get_origin_url(git_root) {
+        Ok(url) => format!("remote:{}", url),
+        Err(_) => format!("path:{}", git_root.display()),
+    }
+}
+
+// <base>/projects/<name>-<id>. A directory already holding the id is reused whatever its name,
+// otherwise one left by an older version is moved into place
+pub fn project_config_dir(base_config_dir: &Path, git_root: &Path) -> PathBuf {
+    let projects_dir = base_config_dir.join("projects");
+    let id = project_id(git_root);
+
+    let suffix = format!("-{}", id);
+    if let Ok(entries) = fs::read_dir(&projects_dir) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().ends_with(&suffix) && entry.path().is_dir() {
+                return entry.path();
+            }
+        }
+    }
+
+    let project_name = git_root
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("unknown");
+    let project_config_dir = projects_dir.join(format!("{}{}", project_name, suffix));
+
+    for old_dir in previous_dirs(&projects_dir, project_name, git_root) {
+        if old_dir.is_dir() {
+            match fs::rename(&old_dir, &project_config_dir) {
+                Ok(()) => eprintln!(
+                    "Moved project config from {} to {}",
+                    old_dir.display(),
+                    project_config_dir.display()
+                ),
+                Err(e) => eprintln!(
+                    "Warning: Failed to move project config from {}: {}",
+                    old_dir.display(),
+                    e
+                ),
+            }
+            break;
+        }
+    }
+    project_config_dir
+}
+
+// Where older versions kept this project: a DefaultHasher of the root path, whose output is not
+// stable across Rust releases, and the ids used until the root commit was known
+fn previous_dirs(projects_dir: &Path, project_name: &str, git_root: &Path) -> Vec<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    git_root.to_path_buf().hash(&mut hasher);
+    let legacy_hash = hasher.finish();
+
+    let mut ids = vec![digest(&fallback_identity(git_root))];
+    let path_id = digest(&format!("path:{}", git_root.display()));
+    if !ids.contains(&path_id) {
+        ids.push(path_id);
+    }
+
+    let mut dirs = vec![projects_dir.join(format!("{}-{:x}", project_name, legacy_hash))];
+    dirs.extend(
+        ids.iter()
+            .map(|id| projects_dir.join(format!("{}-{}", project_name, id))),
+    );
+    dirs
+}
+
+fn digest(identity: &str) -> String {
+    let hash = Sha256::digest(identity.as_bytes());
+    let hex: String = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+    hex[..ID_LENGTH].to_string()
+}
@@ -0,0 +1,321 @@
+use std::collections::BTreeMap;
+
+use regex::{Captures, Regex};
+
+use crate::config::{get_config_key, ConfigPaths, VALID_REDACT_PATTERNS, VALID_SECRET_ACTION};
+use crate::error::{Error, Result};
+use crate::git_integration::rendered_rename_path;
+
+// Built-in detectors. When a pattern has a capture group only the group is masked, so the
+// model still sees which setting changed.
+const DETECTORS: &[(&str, &str)] = &[
+    (
+        "aws_access_key",
+        r"\b(?:AKIA|ASIA|AGPA|AIDA|AROA|AIPA|ANPA|ANVA)[A-Z0-9]{16}\b",
+    ),
+    (
+        "aws_secret_key",
+        r#"(?i)aws_?secret_?(?:access_?)?key["']?\s*[:=]\s*["']?([A-Za-z0-9/+=]{40})"#,
+    ),
+    (
+        "github_token",
+        r"\b(?:gh[pousr]_[A-Za-z0-9]{36,255}|github_pat_[A-Za-z0-9_]{22,255})\b",
+    ),
+];
+
+const CUSTOM_DETECTOR: &str = "custom";
+
+// Random-looking tokens at least this long with at least this many bits per character are
+// treated as secrets; hex digests stay below the threshold
+const ENTROPY_MIN_LENGTH: usize = 24;
+const ENTROPY_THRESHOLD: f64 = 4.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretAction {
+    Redact,
+    Abort,
+}
+
+pub struct Redactor {
+    detectors: Vec<(String, Regex)>,
+    env_assignment: Regex,
+    token: Regex,
+    private_key_begin: Regex,
+    private_key_end: Regex,
+    pem_body: Regex,
+}
+
+// The diff with secrets masked and how many of each kind were found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redaction {
+    pub text: String,
+    pub counts: BTreeMap<String, usize>,
+}
+
+impl Redaction {
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    // e.g. "1 aws_access_key, 2 high_entropy"
+    pub fn describe(&self) -> String {
+        describe(&self.counts)
+    }
+
+    fn found(&mut self, name: &str) {
+        *self.counts.entry(name.to_string()).or_insert(0) += 1;
+    }
+}
+
+impl Redactor {
+    // The built-in detectors plus `patterns`, regular expressions from the redact_patterns key
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let mut detectors: Vec<(String, Regex)> = DETECTORS
+            .iter()
+            .map(|(name, pattern)| {
+                let regex = Regex::new(pattern).expect("built-in detector is a valid regex");
+                (name.to_string(), regex)
+            })
+            .collect();
+        for pattern in patterns {
+            let regex = Regex::new(pattern.as_ref())
+                .map_err(|e| Error::invalid_value(VALID_REDACT_PATTERNS, e))?;
+            detectors.push((CUSTOM_DETECTOR.to_string(), regex));
+        }
+
+        Ok(Redactor {
+            detectors,
+            env_assignment: Regex::new(
+                r"^([+\- ]?\s*(?:export\s+)?[A-Za-z_][A-Za-z0-9_.]*\s*[=:]\s*)(\S.*)$",
+            )
+            .expect("env assignment is a valid regex"),
+            token: Regex::new(r"[A-Za-z0-9+_\-]{24,}").expect("token is a valid regex"),
+            private_key_begin: Regex::new(r"^-----BEGIN [A-Z0-9 ]*PRIVATE KEY-----\s*$")
+                .expect("private key header is a valid regex"),
+            private_key_end: Regex::new(r"^-----END [A-Z0-9 ]*PRIVATE KEY-----\s*$")
+                .expect("private key footer is a valid regex"),
+            // base64 lines, and the headers and blank line of an encrypted key
+            pem_body: Regex::new(r"^(?:[A-Za-z0-9+/=]*|Proc-Type: .*|DEK-Info: .*)\s*$")
+                .expect("pem body is a valid regex"),
+        })
+    }
+
+    pub fn load(config: &ConfigPaths) -> Result<Self> {
+        let values = get_config_key(&[VALID_REDACT_PATTERNS], config)?;
+        Redactor::new(&values[0].split_whitespace().collect::<Vec<&str>>())
+    }
+
+    // Works line by line so the diff keeps its shape; headers pass through untouched
+    pub fn redact(&self, diff: &str) -> Redaction {
+        let mut redaction = Redaction::default();
+        let mut text = String::with_capacity(diff.len());
+        let mut in_header = false;
+        let mut env_file = false;
+        let mut in_private_key = false;
+
+        for line in diff.split_inclusive('\n') {
+            let (content, newline) = match line.strip_suffix('\n') {
+                Some(content) => (content, "\n"),
+                None => (line, ""),
+            };
+
+            // a renamed file keeps only the `old -> new` line of its header
+            if let Some(path) = rendered_rename_path(content) {
+                in_header = false;
+                env_file = is_env_file(path);
+                in_private_key = false;
+                text.push_str(line);
+                continue;
+            }
+            if content.starts_with("diff --git ") {
+                in_header = true;
+                env_file = false;
+                in_private_key = false;
+            } else if content.starts_with("@@") {
+                in_header = false;
+            }
+            if in_header {
+                if let Some(path) = content.strip_prefix("+++ ") {
+                    env_file = is_env_file(path);
+                }
+                text.push_str(line);
+                continue;
+            }
+
+            // the whole block is replaced by one line; it ends at the END line or at the first
+            // line that is not part of a PEM body, so code mentioning the marker is kept
+            let body = &content[diff_prefix(content).len()..];
+            if in_private_key {
+                if self.private_key_end.is_match(body) {
+                    in_private_key = false;
+                    continue;
+                }
+                if self.pem_body.is_match(body) {
+                    continue;
+                }
+                in_private_key = false;
+            }
+            if self.private_key_begin.is_match(body) {
+                redaction.found("private_key");
+                in_private_key = true;
+                text.push_str(diff_prefix(content));
+                text.push_str(&mask("private_key"));
+                text.push_str(newline);
+                continue;
+            }
+
+            let redacted = match self.env_assignment.captures(content) {
+                Some(caps) if env_file => {
+                    redaction.found("env_value");
+                    format!("{}{}", &caps[1], mask("env_value"))
+                }
+                _ => self.redact_line(content, &mut redaction),
+            };
+            text.push_str(&redacted);
+            text.push_str(newline);
+        }
+
+        redaction.text = text;
+        redaction
+    }
+
+    fn redact_line(&self, line: &str, redaction: &mut Redaction) -> String {
+        let mut line = line.to_string();
+        for (name, regex) in &self.detectors {
+            line = regex
+                .replace_all(&line, |caps: &Captures| {
+                    redaction.found(name);
+                    mask_captures(caps, name)
+                })
+                .into_owned();
+        }
+
+        // the diff column and the `=` of an assignment are never part of a token, so the line
+        // keeps its marker and the name of what was set
+        let (column, body) = line.split_at(diff_prefix(&line).len());
+        let body = self
+            .token
+            .replace_all(body, |caps: &Captures| {
+                let token = &caps[0];
+                if is_high_entropy(token) {
+                    redaction.found("high_entropy");
+                    mask("high_entropy")
+                } else {
+                    token.to_string()
+                }
+            })
+            .into_owned();
+        format!("{}{}", column, body)
+    }
+}
+
+pub fn secret_action(config: &ConfigPaths) -> Result<SecretAction> {
+    let values = get_config_key(&[VALID_SECRET_ACTION], config)?;
+    match values[0].as_str() {
+        "abort" => Ok(SecretAction::Abort),
+        _ => Ok(SecretAction::Redact),
+    }
+}
+
+// Runs the configured redaction over a diff before it is sent; with secret_action = abort any
+// finding is an error instead
+pub fn redact_diff(diff: &str, config: &ConfigPaths) -> Result<Redaction> {
+    let redaction = Redactor::load(config)?.redact(diff);
+    if redaction.total() > 0 && secret_action(config)? == SecretAction::Abort {
+        return Err(Error::SecretsFound(redaction.describe()));
+    }
+    Ok(redaction)
+}
+
+// What was masked, counts by detector name, e.g. "1 aws_access_key, 2 high_entropy"
+pub fn describe(counts: &BTreeMap<String, usize>) -> String {
+    counts
+        .iter()
+        .map(|(name, count)| format!("{} {}", count, name))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// Shannon entropy in bits per character, only for tokens mixing upper case, lower case and
+// digits so hex hashes are left alone, and not for CamelCase identifiers
+pub fn is_high_entropy(token: &str) -> bool {
+    if token.len() < ENTROPY_MIN_LENGTH
+        || !token.chars().any(|c| c.is_ascii_uppercase())
+        || !token.chars().any(|c| c.is_ascii_lowercase())
+        || !token.chars().any(|c| c.is_ascii_digit())
+        || is_identifier(token)
+    {
+        return false;
+    }
+
+    let mut counts: BTreeMap<char, usize> = BTreeMap::new();
+    for c in token.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    let length = token.chars().count() as f64;
+    let entropy: f64 = counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / length;
+            -p * p.log2()
+        })
+        .sum();
+    entropy >= ENTROPY_THRESHOLD
+}
+
+// Identifiers such as ValidateUTF8StringInput4Json are made of words several characters
+// long, random strings switch between cases and digits every character or two
+fn is_identifier(token: &str) -> bool {
+    if !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return false;
+    }
+    let chars: Vec<char> = token.chars().collect();
+    let mut words = 1;
+    for i in 1..chars.len() {
+        let (previous, c) = (chars[i - 1], chars[i]);
+        // the last capital of an acronym starts the next word, the P in HTTPProxy
+        let next_lower = chars
+            .get(i + 1)
+            .is_some_and(|next| next.is_ascii_lowercase());
+        if c.is_ascii_digit() != previous.is_ascii_digit()
+            || (c.is_ascii_uppercase()
+                && (previous.is_ascii_lowercase() || previous.is_ascii_uppercase() && next_lower))
+        {
+            words += 1;
+        }
+    }
+    chars.len() >= words * 3
+}
+
+fn mask(name: &str) -> String {
+    format!("[REDACTED {}]", name)
+}
+
+fn mask_captures(caps: &Captures, name: &str) -> String {
+    let whole = caps.get(0).expect("group 0 is always set");
+    match caps.get(1) {
+        Some(secret) => {
+            let text = whole.as_str();
+            let start = secret.start() - whole.start();
+            let end = secret.end() - whole.start();
+            format!("{}{}{}", &text[..start], mask(name), &text[end..])
+        }
+        None => mask(name),
+    }
+}
+
+fn diff_prefix(line: &str) -> &str {
+    match line.chars().next() {
+        Some('+') | Some('-') | Some(' ') => &line[..1],
+        _ => "",
+    }
+}
+
+fn is_env_file(path: &str) -> bool {
+    let name = path
+        .trim_end_matches('\t')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path);
+    name == ".env" || name.starts_with(".env.") || name.ends_with(".env")
+}
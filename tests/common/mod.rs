@@ -0,0 +1,126 @@
+#![allow(dead_code)]
+
+use commit_crafter::config;
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub request_line: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: &str) -> Self {
+        MockResponse {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.to_string(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    // Serves the scripted responses in order, one connection per response
+    pub fn start(responses: Vec<MockResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = match listener.accept() {
+                    Ok(connection) => connection,
+                    Err(_) => return,
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut headers = Vec::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((key, value)) = line.split_once(':') {
+                        let (key, value) = (key.trim().to_string(), value.trim().to_string());
+                        if key.eq_ignore_ascii_case("content-length") {
+                            content_length = value.parse().unwrap_or(0);
+                        }
+                        headers.push((key, value));
+                    }
+                }
+
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                recorded.lock().unwrap().push(RecordedRequest {
+                    request_line: request_line.trim_end().to_string(),
+                    headers,
+                    body: String::from_utf8_lossy(&body).to_string(),
+                });
+
+                let mut raw = format!("HTTP/1.1 {} Mock\r\n", response.status);
+                for (key, value) in &response.headers {
+                    raw.push_str(&format!("{}: {}\r\n", key, value));
+                }
+                raw.push_str(&format!(
+                    "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.body.len(),
+                    response.body
+                ));
+                let _ = stream.write_all(raw.as_bytes());
+            }
+        });
+
+        MockServer { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+// Tests expecting an empty API key must not pick up one exported for other tools, such as
+// OPENAI_API_KEY
+pub fn clear_credential_env() {
+    for key in config::SECRET_KEYS {
+        for var in config::env_vars(key, "") {
+            env::remove_var(var);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use commit_crafter::chunking::{estimate_tokens, join_summaries, split_diff, summary_messages};
+
+fn file_diff(name: &str, hunks: &[&str]) -> String {
+    let mut diff = format!(
+        "diff --git a/{0} b/{0}\nindex 1111111..2222222 100644\n--- a/{0}\n+++ b/{0}\n",
+        name
+    );
+    for hunk in hunks {
+        diff.push_str(hunk);
+    }
+    diff
+}
+
+#[test]
+fn test_estimate_tokens() {
+    assert_eq!(estimate_tokens(""), 0);
+    assert_eq!(estimate_tokens("abcd"), 1);
+    assert_eq!(estimate_tokens("abcde"), 2);
+    // counts characters, not bytes
+    assert_eq!(estimate_tokens("提交信息"), 1);
+}
+
+#[test]
+fn test_small_diff_is_one_chunk() {
+    let diff = file_diff("src/lib.rs", &["@@ -1 +1 @@\n-old\n+new\n"]);
+    assert_eq!(split_diff(&diff, 1000), vec![diff]);
+}
+
+#[test]
+fn test_split_per_file() {
+    let first = file_diff("src/a.rs", &["@@ -1 +1 @@\n-a\n+b\n"]);
+    let second = file_diff("src/b.rs", &["@@ -1 +1 @@\n-c\n+d\n"]);
+    let diff = format!("{}{}", first, second);
+
+    let budget = estimate_tokens(&first).max(estimate_tokens(&second));
+    let chunks = split_diff(&diff, budget);
+
+    assert_eq!(chunks, vec![first, second]);
+}
+
+#[test]
+fn test_split_per_hunk_keeps_file_header() {
+    let hunk_a = format!(
+        "@@ -1,2 +1,2 @@\n-{}\n+{}\n",
+        "a".repeat(80),
+        "b".repeat(80)
+    );
+    let hunk_b = format!(
+        "@@ -40,2 +40,2 @@\n-{}\n+{}\n",
+        "c".repeat(80),
+        "d".repeat(80)
+    );
+    let diff = file_diff("src/big.rs", &[&hunk_a, &hunk_b]);
+
+    let chunks = split_diff(&diff, 80);
+
+    assert_eq!(chunks.len(), 2);
+    assert!(chunks[0].starts_with("diff --git a/src/big.rs b/src/big.rs\n"));
+    assert!(chunks[0].ends_with(&hunk_a));
+    assert!(chunks[1].starts_with("diff --git a/src/big.rs b/src/big.rs\n"));
+    assert!(chunks[1].ends_with(&hunk_b));
+}
+
+#[test]
+fn test_chunks_stay_within_budget() {
+    let lines: String = (0..400).map(|i| format!("+line number {}\n", i)).collect();
+    let hunk = format!("@@ -0,0 +1,400 @@\n{}", lines);
+    let minified = format!("@@ -1 +1 @@\n+{}\n", "z".repeat(3000));
+    let diff = file_diff("src/huge.rs", &[&hunk, &minified]);
+
+    let chunks = split_diff(&diff, 200);
+
+    assert!(chunks.len() > 1);
+    for chunk in &chunks {
+        assert!(estimate_tokens(chunk) <= 200, "{}", estimate_tokens(chunk));
+    }
+    // nothing is lost besides the repeated file headers
+    let total: usize = chunks
+        .iter()
+        .map(|chunk| chunk.matches("+line number").count())
+        .sum();
+    assert_eq!(total, 400);
+    let zs: usize = chunks.iter().map(|chunk| chunk.matches('z').count()).sum();
+    assert_eq!(zs, 3000);
+}
+
+#[test]
+fn test_summary_messages() {
+    let messages = summary_messages("diff_content", 2, 3);
+    assert_eq!(messages[0].role, "system");
+    assert_eq!(
+        messages[1].content,
+        "Part 2 of 3 of the git diff:\ndiff_content"
+    );
+
+    let joined = join_summaries(&["- a".to_string(), "- b".to_string()]);
+    assert!(joined.ends_with("2 parts:\n\n- a\n\n- b"));
+}
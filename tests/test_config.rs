@@ -1,3 +1,5 @@
+mod common;
+
 use commit_crafter::{config, git_integration};
 use std::env;
 use std::fs;
@@ -45,6 +47,7 @@ fn test_set_config_key() {
 
 #[test]
 fn test_get_config_key() {
+    common::clear_credential_env();
     let temp_dir = tempdir().unwrap();
     let file_path = temp_dir.path().join("config.toml");
 
@@ -62,6 +65,7 @@ fn test_get_config_key() {
 
 #[test]
 fn test_list_config_keys() {
+    common::clear_credential_env();
     let temp_dir = tempdir().unwrap();
     let file_path = temp_dir.path().join("config.toml");
 
@@ -86,6 +90,7 @@ fn test_list_config_keys() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_exclude_path() {
     let files_to_exclude = vec![
         "Cargo.lock",
@@ -126,7 +131,7 @@ fn test_set_get_config_key() {
     }
 
     // generate prompt.toml
-    config::move_prompt_toml(&prompt_path);
+    config::move_prompt_toml(&prompt_path).unwrap();
 
     // test set_config_key
     let result = config::set_config_key("openai_api_key", "test_api_key", &config_path);
@@ -250,7 +255,7 @@ fn test_move_prompt_toml() {
     let prompt_path = temp_dir.path().join("prompt.toml");
 
     // Test move_prompt_toml
-    config::move_prompt_toml(&prompt_path);
+    config::move_prompt_toml(&prompt_path).unwrap();
 
     // verify the prompt.toml file exists
     assert!(prompt_path.exists());
@@ -273,15 +278,46 @@ fn test_get_language() {
     let prompt_path = temp_dir.path().join("prompt.toml");
 
     // Test move_prompt_toml
-    config::move_prompt_toml(&prompt_path);
+    config::move_prompt_toml(&prompt_path).unwrap();
 
     // test get_language with different language
-    let en_prompt = config::get_language("en", &prompt_path);
+    let en_prompt = config::get_language("en", &prompt_path).unwrap();
     assert!(en_prompt.contains("English"));
 
-    let zh_prompt = config::get_language("zh", &prompt_path);
+    let zh_prompt = config::get_language("zh", &prompt_path).unwrap();
     assert!(zh_prompt.contains("简体中文"));
 
     // cleanup
     temp_dir.close().unwrap();
 }
+
+#[test]
+fn test_get_provider_from_legacy_config() {
+    let temp_dir = tempdir().unwrap();
+    let file_path = temp_dir.path().join("config.toml");
+
+    // config files written before the provider key existed
+    fs::write(
+        &file_path,
+        "openai_api_key = \"\"\nopenai_url = \"https://api.openai.com\"\nopenai_model = \"gpt-4o-mini\"\nuser_language = \"en\"\n",
+    )
+    .unwrap();
+
+    let value = config::get_config_key(&["provider"], &file_path).unwrap();
+    assert_eq!(value, vec!["openai"]);
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_validate_anthropic_config_keys() {
+    assert!(config::validate_config_key("anthropic_api_key").is_ok());
+    assert!(config::validate_config_key("anthropic_url").is_ok());
+    assert!(config::validate_config_key("anthropic_model").is_ok());
+
+    let keys = ["anthropic_api_key", "anthropic_url", "anthropic_model"];
+    let toml_str = config::generate_config_toml();
+    for key in keys {
+        assert!(toml_str.contains(key));
+    }
+}
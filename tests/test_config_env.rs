@@ -0,0 +1,70 @@
+use commit_crafter::config::{self, ConfigSource};
+use std::env;
+use tempfile::tempdir;
+
+// Everything that reads the environment is in this one test, so no other test sees the variables
+#[test]
+fn test_env_overrides() {
+    let temp_dir = tempdir().unwrap();
+    let file_path = temp_dir.path().join("config.toml");
+    config::write_config_to_toml(&config::generate_config_toml(), &file_path).unwrap();
+    config::set_config_key("openai_api_key", "file_key", &file_path).unwrap();
+
+    // the variable other tools use beats config.toml, the prefixed one beats both
+    env::set_var("OPENAI_API_KEY", "vendor_key");
+    let values = config::get_config_key_with_source(&["openai_api_key"], &file_path).unwrap();
+    assert_eq!(
+        values,
+        vec![(
+            "vendor_key".to_string(),
+            ConfigSource::Env("OPENAI_API_KEY".to_string())
+        )]
+    );
+    env::set_var("AZURE_OPENAI_API_KEY", "azure_key");
+    let values = config::get_config_key_with_source(&["azure_api_key"], &file_path).unwrap();
+    assert_eq!(
+        values,
+        vec![(
+            "azure_key".to_string(),
+            ConfigSource::Env("AZURE_OPENAI_API_KEY".to_string())
+        )]
+    );
+    env::set_var("COMMIT_CRAFTER_OPENAI_API_KEY", "prefixed_key");
+    let values = config::get_config_key(&["openai_api_key"], &file_path).unwrap();
+    assert_eq!(values, vec!["prefixed_key"]);
+
+    // COMMIT_CRAFTER_MODEL sets the model of the provider in use
+    env::set_var("COMMIT_CRAFTER_MODEL", "gpt-4.1");
+    let values = config::get_config_key(&["openai_model", "anthropic_model"], &file_path).unwrap();
+    assert_eq!(values, vec!["gpt-4.1", "claude-3-5-haiku-latest"]);
+    env::set_var("COMMIT_CRAFTER_PROVIDER", "anthropic");
+    let values = config::get_config_key(&["openai_model", "anthropic_model"], &file_path).unwrap();
+    assert_eq!(values, vec!["gpt-4o-mini", "gpt-4.1"]);
+
+    env::set_var("COMMIT_CRAFTER_STREAM", "maybe");
+    match config::get_config_key(&["stream"], &file_path) {
+        Err(e) => assert_eq!(
+            e.to_string(),
+            "Invalid COMMIT_CRAFTER_STREAM: expected true or false"
+        ),
+        Ok(values) => panic!("Expected an invalid value, got {:?}", values),
+    }
+
+    for var in [
+        "OPENAI_API_KEY",
+        "AZURE_OPENAI_API_KEY",
+        "COMMIT_CRAFTER_OPENAI_API_KEY",
+        "COMMIT_CRAFTER_MODEL",
+        "COMMIT_CRAFTER_PROVIDER",
+        "COMMIT_CRAFTER_STREAM",
+    ] {
+        env::remove_var(var);
+    }
+    let values = config::get_config_key_with_source(&["openai_api_key"], &file_path).unwrap();
+    assert_eq!(
+        values,
+        vec![("file_key".to_string(), ConfigSource::Project)]
+    );
+
+    temp_dir.close().unwrap();
+}
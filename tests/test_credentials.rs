@@ -0,0 +1,89 @@
+#![cfg(unix)]
+
+mod common;
+
+use commit_crafter::config::ConfigPaths;
+use commit_crafter::{config, credentials, Error};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use tempfile::tempdir;
+
+#[test]
+fn test_api_key_from_command() {
+    common::clear_credential_env();
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    let config = ConfigPaths::new(temp_dir.path());
+
+    // every run leaves a line in calls, the key is the first line of the output
+    let calls = temp_dir.path().join("calls");
+    let command = format!(
+        "echo run >> {}; echo key-for-{{provider}}; echo ignored",
+        calls.display()
+    );
+    config::set_config_key("api_key_command", &command, &config_path).unwrap();
+
+    let key = credentials::api_key("gemini", "gemini_api_key", &config).unwrap();
+    assert_eq!(key, "key-for-gemini");
+    let key = credentials::api_key("gemini", "gemini_api_key", &config).unwrap();
+    assert_eq!(key, "key-for-gemini");
+    assert_eq!(fs::read_to_string(&calls).unwrap(), "run\n");
+
+    // a key set directly wins over the command
+    config::set_config_key("gemini_api_key", "configured", &config_path).unwrap();
+    let key = credentials::api_key("gemini", "gemini_api_key", &config).unwrap();
+    assert_eq!(key, "configured");
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_failing_api_key_command() {
+    let result = credentials::run_api_key_command("echo locked >&2; exit 3");
+    match result {
+        Err(Error::MissingCredentials(message)) => assert!(message.ends_with(": locked")),
+        other => panic!("Expected missing credentials, got {:?}", other),
+    }
+
+    assert!(matches!(
+        credentials::run_api_key_command("true"),
+        Err(Error::MissingCredentials(_))
+    ));
+}
+
+#[test]
+fn test_api_key_file_must_be_private() {
+    let temp_dir = tempdir().unwrap();
+    let key_file = temp_dir.path().join("openai.key");
+    fs::write(&key_file, "sk-from-file\n").unwrap();
+    let key_path = key_file.to_str().unwrap();
+
+    fs::set_permissions(&key_file, fs::Permissions::from_mode(0o644)).unwrap();
+    match credentials::read_api_key_file(key_path) {
+        Err(e) => assert_eq!(e.exit_code(), 13),
+        Ok(key) => panic!("Expected the readable file to be refused, got {}", key),
+    }
+
+    fs::set_permissions(&key_file, fs::Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(
+        credentials::read_api_key_file(key_path).unwrap(),
+        "sk-from-file"
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_setting_a_key_restricts_the_config_file() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    fs::set_permissions(&config_path, fs::Permissions::from_mode(0o644)).unwrap();
+
+    config::set_config_key("openai_api_key", "sk-test", &config_path).unwrap();
+    let mode = fs::metadata(&config_path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    temp_dir.close().unwrap();
+}
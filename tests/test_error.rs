@@ -0,0 +1,108 @@
+use commit_crafter::llm::ProviderFailure;
+use commit_crafter::{config, Error};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use tempfile::tempdir;
+
+#[test]
+fn test_malformed_config_is_a_parse_error() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, "openai_api_key = \"unterminated\n").unwrap();
+
+    let result = config::get_config_key(&["openai_api_key"], &config_path);
+    assert!(matches!(result, Err(Error::ConfigParse { .. })));
+
+    let result = config::set_config_key("openai_model", "gpt-4o", &config_path);
+    assert!(matches!(result, Err(Error::ConfigParse { .. })));
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_missing_config_is_an_io_error() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+
+    let result = config::get_config_key(&["openai_api_key"], &config_path);
+    match result {
+        Err(Error::ConfigIo { path, .. }) => assert_eq!(path, config_path),
+        other => panic!("expected a config I/O error, got {:?}", other),
+    }
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_invalid_key_and_value() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::write_config_to_toml(&config::generate_config_toml(), &config_path).unwrap();
+
+    let result = config::get_config_key(&["invalid_key"], &config_path);
+    assert!(matches!(result, Err(Error::InvalidKey(key)) if key == "invalid_key"));
+
+    let result = config::set_config_key("stream", "maybe", &config_path);
+    assert!(matches!(result, Err(Error::InvalidValue { key, .. }) if key == "stream"));
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_invalid_language() {
+    let temp_dir = tempdir().unwrap();
+    let prompt_path = temp_dir.path().join("prompt.toml");
+    config::move_prompt_toml(&prompt_path).unwrap();
+
+    let result = config::get_language("fr", &prompt_path);
+    assert!(matches!(result, Err(Error::InvalidLanguage(language)) if language == "fr"));
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_exit_codes_are_distinct() {
+    let errors = [
+        Error::config_io("config.toml", io::Error::other("denied")),
+        Error::config_parse("config.toml", "bad"),
+        Error::InvalidKey("key".to_string()),
+        Error::invalid_value("stream", "bad"),
+        Error::InvalidLanguage("fr".to_string()),
+        Error::MissingCredentials("empty".to_string()),
+        Error::Git("not a repository".to_string()),
+        Error::Io(io::Error::other("io")),
+        Error::Http("refused".to_string()),
+        Error::Timeout("slow".to_string()),
+        Error::ProviderStatus {
+            status: 500,
+            body: String::new(),
+        },
+        Error::ProviderResponse("{}".to_string()),
+        Error::AllProvidersFailed(Vec::new()),
+    ];
+
+    let codes: HashSet<i32> = errors.iter().map(|e| e.exit_code()).collect();
+    assert_eq!(codes.len(), errors.len());
+    assert!(!codes.contains(&0) && !codes.contains(&1));
+    assert_eq!(Error::Timeout(String::new()).exit_code(), 124);
+}
+
+#[test]
+fn test_exit_code_of_a_chain_that_timed_out() {
+    let timeout = |provider: &str| ProviderFailure {
+        provider: provider.to_string(),
+        error: Error::Timeout("slow".to_string()),
+    };
+    let failures = vec![timeout("openai"), timeout("anthropic")];
+    assert_eq!(Error::AllProvidersFailed(failures).exit_code(), 124);
+
+    let failures = vec![
+        timeout("openai"),
+        ProviderFailure {
+            provider: "anthropic".to_string(),
+            error: Error::Http("refused".to_string()),
+        },
+    ];
+    assert_eq!(Error::AllProvidersFailed(failures).exit_code(), 33);
+}
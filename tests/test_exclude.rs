@@ -0,0 +1,91 @@
+use commit_crafter::exclude::{self, ExcludeRules};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_default_excludes() {
+    let rules = ExcludeRules::with_defaults();
+
+    assert!(rules.is_excluded("Cargo.lock"));
+    assert!(rules.is_excluded("package-lock.json"));
+    assert!(rules.is_excluded("web/package-lock.json"));
+    assert!(rules.is_excluded("frontend/pnpm-lock.yaml"));
+    assert!(rules.is_excluded("static/app.min.js"));
+    assert!(rules.is_excluded("tests/__snapshots__/view.test.ts.snap"));
+    assert!(rules.is_excluded("api/service.pb.go"));
+    assert!(!rules.is_excluded("src/main.rs"));
+    assert!(!rules.is_excluded("package.json"));
+}
+
+#[test]
+fn test_unanchored_and_anchored_patterns() {
+    let rules = ExcludeRules::new(&["*.log", "/build", "docs/generated/*.md"]);
+
+    assert!(rules.is_excluded("debug.log"));
+    assert!(rules.is_excluded("logs/deep/debug.log"));
+    assert!(rules.is_excluded("build/output.bin"));
+    assert!(!rules.is_excluded("src/build/output.bin"));
+    assert!(rules.is_excluded("docs/generated/api.md"));
+    assert!(!rules.is_excluded("other/docs/generated/api.md"));
+}
+
+#[test]
+fn test_directory_and_double_star_patterns() {
+    let rules = ExcludeRules::new(&["vendor/", "assets/**/*.svg"]);
+
+    assert!(rules.is_excluded("vendor/lib/a.c"));
+    assert!(rules.is_excluded("third_party/vendor/b.c"));
+    // a directory pattern does not match a file of the same name
+    assert!(!rules.is_excluded("vendor"));
+    assert!(rules.is_excluded("assets/icon.svg"));
+    assert!(rules.is_excluded("assets/icons/small/icon.svg"));
+    assert!(!rules.is_excluded("assets/icon.png"));
+}
+
+#[test]
+fn test_negation_and_classes() {
+    let rules = ExcludeRules::new(&["*.lock", "!important.lock", "file[0-9].txt", "draft[!a].md"]);
+
+    assert!(rules.is_excluded("yarn.lock"));
+    assert!(!rules.is_excluded("important.lock"));
+    assert!(rules.is_excluded("file7.txt"));
+    assert!(!rules.is_excluded("filex.txt"));
+    assert!(rules.is_excluded("draftb.md"));
+    assert!(!rules.is_excluded("drafta.md"));
+
+    // negating a default brings the file back into the diff
+    let mut rules = ExcludeRules::with_defaults();
+    rules.extend(&["!Cargo.lock"]);
+    assert!(!rules.is_excluded("Cargo.lock"));
+    assert!(rules.is_excluded("yarn.lock"));
+}
+
+#[test]
+fn test_extend_from_file() {
+    let temp_dir = tempdir().unwrap();
+    let ignore_file = temp_dir.path().join(exclude::IGNORE_FILE);
+    fs::write(
+        &ignore_file,
+        "# generated clients\nclients/\n\n\\#notes.txt\n*.csv\n",
+    )
+    .unwrap();
+
+    let mut rules = ExcludeRules::default();
+    rules.extend_from_file(&ignore_file).unwrap();
+
+    assert!(rules.is_excluded("clients/api.ts"));
+    assert!(rules.is_excluded("#notes.txt"));
+    assert!(rules.is_excluded("data/export.csv"));
+    assert!(!rules.is_excluded("src/lib.rs"));
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_split_patterns() {
+    assert_eq!(
+        exclude::split_patterns(" *.csv, fixtures/ ,,!keep.csv"),
+        vec!["*.csv", "fixtures/", "!keep.csv"]
+    );
+    assert!(exclude::split_patterns("").is_empty());
+}
@@ -1,4 +1,7 @@
-use commit_crafter::git_integration;
+use commit_crafter::exclude::ExcludeRules;
+use commit_crafter::git_integration::{
+    self, ChangeSet, DiffOptions, DiffSource, FileStat, FileStatus,
+};
 use std::env;
 
 #[test]
@@ -99,6 +102,7 @@ fn test_get_recent_commits_large_count() {
 }
 
 #[test]
+#[allow(clippy::assertions_on_constants)]
 fn test_run_git_diff_still_works() {
     if env::var("GITHUB_ACTIONS").is_ok() {
         eprintln!("Skipping test in GitHub Actions environment");
@@ -106,7 +110,7 @@ fn test_run_git_diff_still_works() {
     }
 
     // 确保原有的git diff功能仍然正常工作
-    let result = git_integration::run_git_diff();
+    let result = git_integration::run_git_diff(&DiffOptions::default());
 
     match result {
         Ok(_output) => {
@@ -119,3 +123,306 @@ fn test_run_git_diff_still_works() {
         }
     }
 }
+
+#[test]
+fn test_parse_numstat() {
+    let output = concat!(
+        "3\t1\tsrc/main.rs\0",
+        "-\t-\tlogo.png\0",
+        "0\t0\t\0old/name.rs\0new/name.rs\0"
+    );
+    let stats = git_integration::parse_numstat(output);
+
+    assert_eq!(
+        stats,
+        vec![
+            FileStat {
+                path: "src/main.rs".to_string(),
+                old_path: None,
+                additions: Some(3),
+                deletions: Some(1),
+            },
+            FileStat {
+                path: "logo.png".to_string(),
+                old_path: None,
+                additions: None,
+                deletions: None,
+            },
+            FileStat {
+                path: "new/name.rs".to_string(),
+                old_path: Some("old/name.rs".to_string()),
+                additions: Some(0),
+                deletions: Some(0),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_format_excluded() {
+    let stats = git_integration::parse_numstat("120\t4\tCargo.lock\0-\t-\tdist/app.min.js\0");
+    let listing = git_integration::format_excluded(&stats.iter().collect::<Vec<&FileStat>>());
+
+    assert!(listing.contains("Cargo.lock (+120 -4)\n"));
+    assert!(listing.contains("dist/app.min.js (binary)\n"));
+}
+
+const STRUCTURED_DIFF: &str = concat!(
+    "diff --git a/src/lib.rs b/src/lib.rs\n",
+    "index 1111111..2222222 100644\n",
+    "--- a/src/lib.rs\n",
+    "+++ b/src/lib.rs\n",
+    "@@ -1,2 +1,2 @@\n",
+    " pub mod config;\n",
+    "-pub mod old;\n",
+    "+pub mod new;\n",
+    "diff --git a/docs/new.md b/docs/new.md\n",
+    "new file mode 100644\n",
+    "index 0000000..3333333\n",
+    "--- /dev/null\n",
+    "+++ b/docs/new.md\n",
+    "@@ -0,0 +1 @@\n",
+    "+# New\n",
+    "diff --git a/old_name.rs b/new_name.rs\n",
+    "similarity index 100%\n",
+    "rename from old_name.rs\n",
+    "rename to new_name.rs\n",
+    "diff --git a/run.sh b/run.sh\n",
+    "old mode 100644\n",
+    "new mode 100755\n",
+    "diff --git a/logo.png b/logo.png\n",
+    "deleted file mode 100644\n",
+    "index 4444444..0000000\n",
+    "Binary files a/logo.png and /dev/null differ\n",
+);
+
+#[test]
+fn test_parse_diff() {
+    let files = git_integration::parse_diff(STRUCTURED_DIFF);
+    assert_eq!(files.len(), 5);
+
+    assert_eq!(files[0].path, "src/lib.rs");
+    assert_eq!(files[0].status, FileStatus::Modified);
+    assert_eq!((files[0].additions, files[0].deletions), (1, 1));
+    assert!(files[0].patch.starts_with("diff --git a/src/lib.rs"));
+    assert!(files[0].patch.ends_with("+pub mod new;\n"));
+
+    assert_eq!(files[1].path, "docs/new.md");
+    assert_eq!(files[1].status, FileStatus::Added);
+    assert_eq!(files[1].new_mode.as_deref(), Some("100644"));
+    assert_eq!(files[1].additions, 1);
+
+    assert_eq!(files[2].path, "new_name.rs");
+    assert_eq!(files[2].old_path.as_deref(), Some("old_name.rs"));
+    assert_eq!(files[2].status, FileStatus::Renamed);
+    assert_eq!(files[2].similarity, Some(100));
+
+    assert_eq!(files[3].path, "run.sh");
+    assert_eq!(files[3].old_mode.as_deref(), Some("100644"));
+    assert_eq!(files[3].new_mode.as_deref(), Some("100755"));
+
+    assert_eq!(files[4].path, "logo.png");
+    assert_eq!(files[4].status, FileStatus::Deleted);
+    assert!(files[4].binary);
+}
+
+#[test]
+fn test_parse_diff_paths_with_spaces() {
+    let diff = concat!(
+        "diff --git a/my file.txt b/my file.txt\n",
+        "old mode 100644\n",
+        "new mode 100755\n",
+        "diff --git \"a/tab\\tname\" \"b/tab\\tname\"\n",
+        "old mode 100644\n",
+        "new mode 100755\n",
+    );
+    let files = git_integration::parse_diff(diff);
+
+    assert_eq!(files[0].path, "my file.txt");
+    assert_eq!(files[1].path, "tab\tname");
+}
+
+#[test]
+fn test_staged_change_render() {
+    let mut generated = String::from(concat!(
+        "diff --git a/src/schema.rs b/src/schema.rs\n",
+        "--- a/src/schema.rs\n",
+        "+++ b/src/schema.rs\n",
+        "@@ -1,600 +1,600 @@\n",
+        " // @generated by diesel\n",
+    ));
+    for i in 0..600 {
+        generated.push_str(&format!("-old {}\n+new {}\n", i, i));
+    }
+    let diff = format!("{}{}", STRUCTURED_DIFF, generated);
+    let stats = git_integration::parse_numstat(concat!(
+        "1\t1\tsrc/lib.rs\0",
+        "-\t-\tlogo.png\0",
+        "120\t4\tCargo.lock\0"
+    ));
+    let change = ChangeSet::parse(&diff, &stats, &ExcludeRules::with_defaults());
+
+    assert_eq!(change.files.len(), 6);
+    assert_eq!(change.excluded.len(), 1);
+    assert!(change.files[5].generated);
+    assert_eq!(change.files[5].additions, 600);
+
+    let rendered = change.render();
+    assert!(rendered.contains("+pub mod new;\n"));
+    assert!(rendered.contains("renamed old_name.rs -> new_name.rs (100% similar)\n"));
+    assert!(!rendered.contains("rename to"));
+    assert!(!rendered.contains("Binary files"));
+    assert!(!rendered.contains("+new 1\n"));
+    assert!(rendered.contains("logo.png (deleted, binary)\n"));
+    assert!(rendered.contains("src/schema.rs (modified, generated, +600 -600)\n"));
+    assert!(rendered.contains("Cargo.lock (+120 -4)\n"));
+
+    assert!(ChangeSet::default().render().is_empty());
+}
+
+#[test]
+fn test_file_change_summary() {
+    let files = git_integration::parse_diff(STRUCTURED_DIFF);
+
+    assert_eq!(
+        files[2].summary(),
+        "old_name.rs -> new_name.rs (renamed, +0 -0)"
+    );
+    assert_eq!(
+        files[3].summary(),
+        "run.sh (modified, +0 -0, mode 100644 -> 100755)"
+    );
+}
+
+#[test]
+fn test_rename_shows_only_the_delta() {
+    let diff = concat!(
+        "diff --git a/src/util.rs b/src/helpers/util.rs\n",
+        "similarity index 92%\n",
+        "rename from src/util.rs\n",
+        "rename to src/helpers/util.rs\n",
+        "index 1111111..2222222 100644\n",
+        "--- a/src/util.rs\n",
+        "+++ b/src/helpers/util.rs\n",
+        "@@ -1,3 +1,3 @@\n",
+        "-use crate::config;\n",
+        "+use crate::helpers::config;\n",
+    );
+    let files = git_integration::parse_diff(diff);
+
+    assert_eq!(files[0].status, FileStatus::Renamed);
+    assert_eq!(files[0].path, "src/helpers/util.rs");
+    assert_eq!(
+        files[0].render(),
+        concat!(
+            "renamed src/util.rs -> src/helpers/util.rs (92% similar)\n",
+            "@@ -1,3 +1,3 @@\n",
+            "-use crate::config;\n",
+            "+use crate::helpers::config;\n",
+        )
+    );
+}
+
+#[test]
+fn test_diff_source_revisions() {
+    assert_eq!(DiffSource::Staged.revisions().unwrap(), vec!["--staged"]);
+    assert!(DiffSource::Unstaged.revisions().unwrap().is_empty());
+    assert_eq!(DiffSource::All.revisions().unwrap(), vec!["HEAD"]);
+    assert_eq!(
+        DiffSource::Range("v1.0..main".to_string())
+            .revisions()
+            .unwrap(),
+        vec!["v1.0..main"]
+    );
+    assert!(DiffSource::Range("main".to_string()).revisions().is_err());
+
+    assert_eq!(DiffSource::Staged.history_base(), None);
+    assert_eq!(
+        DiffSource::Commit("abc123".to_string()).history_base(),
+        Some("abc123^".to_string())
+    );
+    assert_eq!(
+        DiffSource::Range("v1.0...main".to_string()).history_base(),
+        Some("v1.0".to_string())
+    );
+}
+
+#[test]
+fn test_diff_existing_commit() {
+    if env::var("GITHUB_ACTIONS").is_ok() {
+        eprintln!("Skipping test in GitHub Actions environment");
+        return;
+    }
+
+    match DiffSource::Commit("HEAD".to_string()).revisions() {
+        Ok(revisions) => {
+            assert_eq!(revisions.len(), 2);
+            assert!(revisions.iter().all(|rev| rev.len() == 40));
+
+            let options = DiffOptions {
+                source: DiffSource::Commit("HEAD".to_string()),
+                ..DiffOptions::default()
+            };
+            assert!(!git_integration::run_git_diff(&options).unwrap().is_empty());
+        }
+        Err(e) => {
+            // 新仓库可能还没有commit
+            eprintln!("Warning: Could not resolve HEAD: {}", e);
+        }
+    }
+
+    match DiffSource::Commit("no-such-revision".to_string()).revisions() {
+        Err(e) => assert_eq!(e.to_string(), "Unknown commit no-such-revision"),
+        Ok(revisions) => panic!("Expected an unknown revision, got {:?}", revisions),
+    }
+}
+
+#[test]
+fn test_changes_from_patch() {
+    let patch = format!(
+        concat!(
+            "From 1234567890abcdef Mon Sep 17 00:00:00 2001\n",
+            "From: Someone <someone@example.com>\n",
+            "Subject: [PATCH] feat: the existing message\n",
+            "\n",
+            "---\n",
+            " src/lib.rs | 2 +-\n",
+            "\n",
+            "{}",
+            "diff --git a/Cargo.lock b/Cargo.lock\n",
+            "--- a/Cargo.lock\n",
+            "+++ b/Cargo.lock\n",
+            "@@ -1 +1,2 @@\n",
+            " version = 3\n",
+            "+[[package]]\n",
+        ),
+        STRUCTURED_DIFF
+    );
+    let options = DiffOptions {
+        source: DiffSource::Patch(patch),
+        ..DiffOptions::default()
+    };
+    let change = git_integration::collect_changes(&options).unwrap();
+
+    assert_eq!(change.files.len(), 5);
+    assert_eq!(
+        change.excluded,
+        vec![FileStat {
+            path: "Cargo.lock".to_string(),
+            old_path: None,
+            additions: Some(1),
+            deletions: Some(0),
+        }]
+    );
+    let rendered = change.render();
+    assert!(rendered.starts_with("diff --git a/src/lib.rs b/src/lib.rs\n"));
+    assert!(!rendered.contains("the existing message"));
+    assert!(rendered.contains("Cargo.lock (+1 -0)\n"));
+
+    // a plain `diff -u` patch has no git headers and is sent as it is
+    let plain = "--- old.txt\n+++ new.txt\n@@ -1 +1 @@\n-a\n+b\n";
+    let change = ChangeSet::from_patch(plain, &ExcludeRules::with_defaults());
+    assert!(change.files.is_empty());
+    assert_eq!(change.render(), plain);
+    assert!(ChangeSet::from_patch("\n", &ExcludeRules::with_defaults()).is_empty());
+}
@@ -0,0 +1,159 @@
+use commit_crafter::config::ConfigPaths;
+use commit_crafter::{config, llm};
+use std::sync::Mutex;
+use tempfile::tempdir;
+
+struct FakeProvider {
+    reply: String,
+    received: Mutex<Vec<llm::Message>>,
+}
+
+impl llm::LlmProvider for FakeProvider {
+    fn name(&self) -> &str {
+        "fake"
+    }
+
+    fn model(&self) -> &str {
+        "fake-model"
+    }
+
+    fn generate(&self, messages: &[llm::Message]) -> commit_crafter::Result<llm::Completion> {
+        *self.received.lock().unwrap() = messages.to_vec();
+        Ok(llm::Completion::new(&self.reply))
+    }
+}
+
+#[test]
+fn test_build_messages() {
+    let commit_history = vec!["feat: add new feature".to_string()];
+    let messages = llm::build_messages("base prompt", "diff_content", &commit_history);
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0], llm::Message::system("base prompt"));
+    assert_eq!(messages[1].role, "user");
+    assert!(messages[1]
+        .content
+        .starts_with("Recent commit messages for reference:\n1. feat: add new feature\n"));
+    assert!(messages[1]
+        .content
+        .ends_with("Git diff content:\ndiff_content"));
+}
+
+#[test]
+fn test_build_messages_with_empty_history() {
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+    assert_eq!(messages[1].content, "Git diff content:\ndiff_content");
+}
+
+#[test]
+fn test_generate_with_fake_provider() {
+    let temp_dir = tempdir().unwrap();
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+
+    let provider = FakeProvider {
+        reply: "feat: fake message".to_string(),
+        received: Mutex::new(Vec::new()),
+    };
+    let result = llm::generate_with_provider(
+        &provider,
+        "diff_content",
+        &[],
+        &ConfigPaths::new(temp_dir.path()),
+    );
+
+    let message = result.unwrap();
+    assert_eq!(message.text, "feat: fake message");
+    assert_eq!(message.provider, "fake");
+    assert_eq!(message.model, "fake-model");
+    let received = provider.received.lock().unwrap();
+    assert!(received[0].content.contains("English"));
+    assert!(received[1].content.contains("diff_content"));
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_create_provider_unknown() {
+    let temp_dir = tempdir().unwrap();
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+
+    let result = llm::create_provider("unknown", &ConfigPaths::new(temp_dir.path()));
+    match result {
+        Ok(_) => panic!("Expected an unknown provider error"),
+        Err(e) => assert_eq!(e.to_string(), "Invalid provider: unknown"),
+    }
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_provider_from_config_defaults_to_openai() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    config::set_config_key("openai_api_key", "test_key", &config_path).unwrap();
+
+    let provider = llm::provider_from_config(&ConfigPaths::new(temp_dir.path())).unwrap();
+    assert_eq!(provider.name(), "openai");
+    assert_eq!(provider.model(), "gpt-4o-mini");
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_set_invalid_provider() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+
+    let result = config::set_config_key("provider", "unknown", &config_path);
+    assert!(result.is_err());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_default_generate_stream_emits_whole_message() {
+    let provider = FakeProvider {
+        reply: "feat: fake message".to_string(),
+        received: Mutex::new(Vec::new()),
+    };
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let mut tokens = Vec::new();
+    let result = llm::LlmProvider::generate_stream(&provider, &messages, &mut |token| {
+        tokens.push(token.to_string())
+    })
+    .unwrap();
+
+    assert_eq!(result.text, "feat: fake message");
+    assert_eq!(tokens, vec!["feat: fake message"]);
+}
+
+#[test]
+fn test_read_sse_stream() {
+    let stream = "event: ping\ndata: first\n\ndata:second\n\ndata: [DONE]\n\ndata: ignored\n";
+    let mut payloads = Vec::new();
+    llm::sse::read_sse_stream(stream.as_bytes(), |data| {
+        payloads.push(data.to_string());
+        Ok(())
+    })
+    .unwrap();
+
+    assert_eq!(payloads, vec!["first", "second"]);
+}
+
+#[test]
+fn test_streaming_enabled() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    let config = ConfigPaths::new(temp_dir.path());
+
+    assert!(llm::streaming_enabled(&config));
+    config::set_config_key("stream", "false", &config_path).unwrap();
+    assert!(!llm::streaming_enabled(&config));
+    assert!(config::set_config_key("stream", "yes", &config_path).is_err());
+
+    temp_dir.close().unwrap();
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use commit_crafter::config::{self, ConfigPaths};
+use commit_crafter::llm::anthropic::AnthropicProvider;
+use commit_crafter::llm::{self, LlmProvider};
+use commit_crafter::Error;
+use common::{MockResponse, MockServer};
+use serde_json::Value;
+use tempfile::tempdir;
+
+#[test]
+fn test_anthropic_request() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"id":"msg_1","type":"message","role":"assistant","content":[{"type":"text","text":"feat: add anthropic backend"}],"model":"claude-3-5-haiku-latest","usage":{"input_tokens":31,"output_tokens":7}}"#,
+    )]);
+    let provider = AnthropicProvider::new("test_key", &server.url, "claude-3-5-haiku-latest");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let result = provider.generate(&messages).unwrap();
+    assert_eq!(result.text, "feat: add anthropic backend");
+    assert_eq!(result.usage.map(|usage| usage.total()), Some(38));
+
+    let requests = server.requests();
+    assert_eq!(requests[0].request_line, "POST /v1/messages HTTP/1.1");
+    assert_eq!(requests[0].header("x-api-key"), Some("test_key"));
+    assert_eq!(requests[0].header("anthropic-version"), Some("2023-06-01"));
+
+    let body: Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["model"], "claude-3-5-haiku-latest");
+    assert_eq!(body["system"], "base prompt");
+    assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+    assert_eq!(body["messages"][0]["role"], "user");
+}
+
+#[test]
+fn test_anthropic_error_body() {
+    let server = MockServer::start(vec![MockResponse::json(
+        401,
+        r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#,
+    )]);
+    let provider = AnthropicProvider::new("bad_key", &server.url, "claude-3-5-haiku-latest");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    match provider.generate(&messages) {
+        Err(Error::ProviderStatus { status, body }) => {
+            assert_eq!(status, 401);
+            assert_eq!(body, "authentication_error: invalid x-api-key");
+        }
+        other => panic!("Expected a status error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_anthropic_unexpected_response() {
+    let server = MockServer::start(vec![MockResponse::json(200, r#"{"content":[]}"#)]);
+    let provider = AnthropicProvider::new("test_key", &server.url, "claude-3-5-haiku-latest");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    assert!(matches!(
+        provider.generate(&messages),
+        Err(Error::ProviderResponse(_))
+    ));
+}
+
+#[test]
+fn test_anthropic_from_config() {
+    common::clear_credential_env();
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    let config = ConfigPaths::new(temp_dir.path());
+
+    match AnthropicProvider::from_config(&config) {
+        Ok(_) => panic!("Expected an error for the empty API key"),
+        Err(e) => assert_eq!(e.to_string(), "Anthropic API key or URL is empty"),
+    }
+
+    config::set_config_key("provider", "anthropic", &config_path).unwrap();
+    config::set_config_key("anthropic_api_key", "test_key", &config_path).unwrap();
+    config::set_config_key("anthropic_model", "claude-3-5-sonnet-latest", &config_path).unwrap();
+
+    let provider = llm::provider_from_config(&config).unwrap();
+    assert_eq!(provider.name(), "anthropic");
+    assert_eq!(provider.model(), "claude-3-5-sonnet-latest");
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_anthropic_stream() {
+    let server = MockServer::start(vec![MockResponse {
+        status: 200,
+        headers: vec![("Content-Type".to_string(), "text/event-stream".to_string())],
+        body: concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"usage\":{\"input_tokens\":31,\"output_tokens\":1}}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"fix: \"}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"stream parsing\"}}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":4}}\n\n",
+            "event: message_stop\n",
+            "data: {\"type\":\"message_stop\"}\n\n"
+        )
+        .to_string(),
+    }]);
+    let provider = AnthropicProvider::new("test_key", &server.url, "claude-3-5-haiku-latest");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let mut tokens = Vec::new();
+    let result = provider
+        .generate_stream(&messages, &mut |token| tokens.push(token.to_string()))
+        .unwrap();
+
+    assert_eq!(result.text, "fix: stream parsing");
+    assert_eq!(tokens, vec!["fix: ", "stream parsing"]);
+    assert_eq!(result.usage.map(|usage| usage.total()), Some(35));
+}
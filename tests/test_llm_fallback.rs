@@ -0,0 +1,153 @@
+mod common;
+
+use commit_crafter::config::{self, ConfigPaths};
+use commit_crafter::llm::{self, ProviderSpec};
+use commit_crafter::Error;
+use common::{MockResponse, MockServer};
+use std::path::Path;
+use tempfile::tempdir;
+
+const CHAT_COMPLETION: &str = r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"feat: fall back to openai"}}]}"#;
+
+fn closed_port_url() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+fn setup_config(dir: &Path, openai_url: &str) {
+    let config_path = dir.join("config.toml");
+    config::ensure_config_initialized(dir).unwrap();
+    config::set_config_key("provider", "ollama", &config_path).unwrap();
+    config::set_config_key("ollama_url", &closed_port_url(), &config_path).unwrap();
+    config::set_config_key("fallback_providers", "openai:gpt-4o", &config_path).unwrap();
+    config::set_config_key("openai_api_key", "test_key", &config_path).unwrap();
+    config::set_config_key("openai_url", openai_url, &config_path).unwrap();
+    config::set_config_key("retry_max_attempts", "1", &config_path).unwrap();
+}
+
+#[test]
+fn test_provider_spec_parse() {
+    assert_eq!(
+        ProviderSpec::parse(" ollama:llama3.2 "),
+        ProviderSpec {
+            name: "ollama".to_string(),
+            model: Some("llama3.2".to_string()),
+        }
+    );
+    assert_eq!(ProviderSpec::parse("openai").model, None);
+    assert_eq!(
+        ProviderSpec::parse("openai:gpt-4o").to_string(),
+        "openai:gpt-4o"
+    );
+}
+
+#[test]
+fn test_provider_chain_from_config() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    let config = ConfigPaths::new(temp_dir.path());
+
+    let chain = llm::provider_chain_from_config(&config).unwrap();
+    assert_eq!(chain, vec![ProviderSpec::parse("openai")]);
+
+    config::set_config_key("provider", "ollama", &config_path).unwrap();
+    config::set_config_key(
+        "fallback_providers",
+        "openai:gpt-4o, anthropic",
+        &config_path,
+    )
+    .unwrap();
+    let chain = llm::provider_chain_from_config(&config).unwrap();
+    let names: Vec<String> = chain.iter().map(|spec| spec.to_string()).collect();
+    assert_eq!(names, vec!["ollama", "openai:gpt-4o", "anthropic"]);
+
+    assert!(config::set_config_key("fallback_providers", "openai,unknown", &config_path).is_err());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_fallback_on_unreachable_provider() {
+    let server = MockServer::start(vec![MockResponse::json(200, CHAT_COMPLETION)]);
+    let temp_dir = tempdir().unwrap();
+    setup_config(temp_dir.path(), &server.url);
+
+    let generation =
+        llm::generate_commit_message("diff_content", &[], &ConfigPaths::new(temp_dir.path()))
+            .unwrap();
+
+    assert_eq!(generation.text, "feat: fall back to openai");
+    assert_eq!(generation.provider, "openai");
+    assert_eq!(generation.model, "gpt-4o");
+    assert_eq!(generation.failures.len(), 1);
+    assert_eq!(generation.failures[0].provider, "ollama");
+    assert!(matches!(generation.failures[0].error, Error::Http(_)));
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_fallback_on_rate_limit() {
+    let server = MockServer::start(vec![
+        MockResponse::json(429, "{}"),
+        MockResponse::json(200, CHAT_COMPLETION),
+    ]);
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    setup_config(temp_dir.path(), &server.url);
+    config::set_config_key("provider", "openai", &config_path).unwrap();
+    config::set_config_key("fallback_providers", "openai:gpt-4o", &config_path).unwrap();
+
+    let generation =
+        llm::generate_commit_message("diff_content", &[], &ConfigPaths::new(temp_dir.path()))
+            .unwrap();
+
+    assert_eq!(generation.model, "gpt-4o");
+    assert_eq!(generation.failures[0].provider, "openai");
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_no_fallback_on_bad_request() {
+    let server = MockServer::start(vec![MockResponse::json(400, "{}")]);
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    setup_config(temp_dir.path(), &server.url);
+    config::set_config_key("provider", "openai", &config_path).unwrap();
+    config::set_config_key("fallback_providers", "ollama", &config_path).unwrap();
+
+    let result =
+        llm::generate_commit_message("diff_content", &[], &ConfigPaths::new(temp_dir.path()));
+    assert!(matches!(
+        result,
+        Err(Error::ProviderStatus { status: 400, .. })
+    ));
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_all_providers_failed() {
+    common::clear_credential_env();
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    setup_config(temp_dir.path(), &closed_port_url());
+    config::set_config_key("fallback_providers", "openai,gemini", &config_path).unwrap();
+
+    match llm::generate_commit_message("diff_content", &[], &ConfigPaths::new(temp_dir.path())) {
+        Err(Error::AllProvidersFailed(failures)) => {
+            let providers: Vec<&str> = failures.iter().map(|f| f.provider.as_str()).collect();
+            assert_eq!(providers, vec!["ollama", "openai", "gemini"]);
+            // gemini has no API key configured
+            assert_eq!(
+                failures[2].error.to_string(),
+                "Gemini API key or URL is empty"
+            );
+        }
+        other => panic!("Expected every provider to fail, got {:?}", other),
+    }
+
+    temp_dir.close().unwrap();
+}
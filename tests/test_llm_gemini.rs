@@ -0,0 +1,81 @@
+mod common;
+
+use commit_crafter::config::{self, ConfigPaths};
+use commit_crafter::llm::gemini::GeminiProvider;
+use commit_crafter::llm::{self, LlmProvider};
+use commit_crafter::Error;
+use common::{MockResponse, MockServer};
+use serde_json::Value;
+use tempfile::tempdir;
+
+#[test]
+fn test_gemini_request() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"feat: add gemini backend\n"}]},"finishReason":"STOP"}],"usageMetadata":{"promptTokenCount":20,"candidatesTokenCount":5,"totalTokenCount":25}}"#,
+    )]);
+    let provider = GeminiProvider::new("test_key", &server.url, "gemini-1.5-flash");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let result = provider.generate(&messages).unwrap();
+    assert_eq!(result.text, "feat: add gemini backend");
+    assert_eq!(result.usage.map(|usage| usage.total()), Some(25));
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].request_line,
+        "POST /v1beta/models/gemini-1.5-flash:generateContent HTTP/1.1"
+    );
+    assert_eq!(requests[0].header("x-goog-api-key"), Some("test_key"));
+
+    let body: Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(
+        body["system_instruction"]["parts"][0]["text"],
+        "base prompt"
+    );
+    assert_eq!(body["contents"][0]["role"], "user");
+    assert_eq!(
+        body["contents"][0]["parts"][0]["text"],
+        "Git diff content:\ndiff_content"
+    );
+}
+
+#[test]
+fn test_gemini_error_body() {
+    let server = MockServer::start(vec![MockResponse::json(
+        400,
+        r#"{"error":{"code":400,"message":"API key not valid.","status":"INVALID_ARGUMENT"}}"#,
+    )]);
+    let provider = GeminiProvider::new("bad_key", &server.url, "gemini-1.5-flash");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    match provider.generate(&messages) {
+        Err(Error::ProviderStatus { status, body }) => {
+            assert_eq!(status, 400);
+            assert_eq!(body, "API key not valid.");
+        }
+        other => panic!("Expected a status error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_gemini_from_config() {
+    common::clear_credential_env();
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    config::set_config_key("provider", "gemini", &config_path).unwrap();
+    let config = ConfigPaths::new(temp_dir.path());
+
+    match llm::provider_from_config(&config) {
+        Ok(_) => panic!("Expected an error for the empty API key"),
+        Err(e) => assert_eq!(e.to_string(), "Gemini API key or URL is empty"),
+    }
+
+    config::set_config_key("gemini_api_key", "test_key", &config_path).unwrap();
+    let provider = llm::provider_from_config(&config).unwrap();
+    assert_eq!(provider.name(), "gemini");
+    assert_eq!(provider.model(), "gemini-1.5-flash");
+
+    temp_dir.close().unwrap();
+}
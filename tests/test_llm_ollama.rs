@@ -0,0 +1,144 @@
+mod common;
+
+use commit_crafter::config::{self, ConfigPaths};
+use commit_crafter::llm::ollama::{parse_ndjson_response, OllamaProvider};
+use commit_crafter::llm::{self, LlmProvider};
+use commit_crafter::Error;
+use common::{MockResponse, MockServer};
+use serde_json::Value;
+use tempfile::tempdir;
+
+#[test]
+fn test_ollama_chat_request() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"model":"llama3.2","message":{"role":"assistant","content":"feat: add ollama backend"},"done":true,"prompt_eval_count":26,"eval_count":5}"#,
+    )]);
+    let provider = OllamaProvider::new(&server.url, "llama3.2", "chat");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let result = provider.generate(&messages).unwrap();
+    assert_eq!(result.text, "feat: add ollama backend");
+    assert_eq!(result.usage.map(|usage| usage.total()), Some(31));
+
+    let requests = server.requests();
+    assert_eq!(requests[0].request_line, "POST /api/chat HTTP/1.1");
+    assert_eq!(requests[0].header("authorization"), None);
+
+    let body: Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["model"], "llama3.2");
+    assert_eq!(body["stream"], false);
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][1]["role"], "user");
+}
+
+#[test]
+fn test_ollama_generate_request() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"model":"llama3.2","response":"fix: handle empty diff","done":true}"#,
+    )]);
+    let provider = OllamaProvider::new(&server.url, "llama3.2", "generate");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let result = provider.generate(&messages).unwrap();
+    assert_eq!(result.text, "fix: handle empty diff");
+
+    let requests = server.requests();
+    assert_eq!(requests[0].request_line, "POST /api/generate HTTP/1.1");
+    let body: Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["system"], "base prompt");
+    assert_eq!(body["prompt"], "Git diff content:\ndiff_content");
+}
+
+#[test]
+fn test_ollama_streamed_response() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        concat!(
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"feat: \"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"stream \"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"tokens\"},\"done\":false}\n",
+            "{\"done\":true,\"total_duration\":1000}\n"
+        ),
+    )]);
+    let provider = OllamaProvider::new(&server.url, "llama3.2", "chat");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    assert_eq!(
+        provider.generate(&messages).unwrap().text,
+        "feat: stream tokens"
+    );
+}
+
+#[test]
+fn test_parse_ndjson_generate_chunks() {
+    let body = "{\"response\":\"docs: \",\"done\":false}\n{\"response\":\"update README\",\"done\":false}\n{\"response\":\"\",\"done\":true}\n";
+    assert_eq!(
+        parse_ndjson_response(body).unwrap().text,
+        "docs: update README"
+    );
+
+    assert!(parse_ndjson_response("").is_err());
+    assert!(parse_ndjson_response("not json").is_err());
+}
+
+#[test]
+fn test_ollama_error_body() {
+    let server = MockServer::start(vec![MockResponse::json(
+        404,
+        r#"{"error":"model 'missing' not found"}"#,
+    )]);
+    let provider = OllamaProvider::new(&server.url, "missing", "chat");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    match provider.generate(&messages) {
+        Err(Error::ProviderStatus { status, body }) => {
+            assert_eq!(status, 404);
+            assert_eq!(body, "model 'missing' not found");
+        }
+        other => panic!("Expected a status error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_ollama_from_config_without_api_key() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    config::set_config_key("provider", "ollama", &config_path).unwrap();
+
+    let provider = llm::provider_from_config(&ConfigPaths::new(temp_dir.path())).unwrap();
+    assert_eq!(provider.name(), "ollama");
+    assert_eq!(provider.model(), "llama3.2");
+
+    assert!(config::set_config_key("ollama_api", "invalid", &config_path).is_err());
+    assert!(config::set_config_key("ollama_api", "generate", &config_path).is_ok());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_ollama_generate_stream() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        concat!(
+            "{\"response\":\"chore: \",\"done\":false}\n",
+            "{\"response\":\"bump deps\",\"done\":false}\n",
+            "{\"response\":\"\",\"done\":true}\n"
+        ),
+    )]);
+    let provider = OllamaProvider::new(&server.url, "llama3.2", "generate");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let mut tokens = Vec::new();
+    let result = provider
+        .generate_stream(&messages, &mut |token| tokens.push(token.to_string()))
+        .unwrap();
+
+    assert_eq!(result.text, "chore: bump deps");
+    assert_eq!(tokens, vec!["chore: ", "bump deps", ""]);
+
+    let body: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+    assert_eq!(body["stream"], true);
+}
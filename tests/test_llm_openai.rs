@@ -1,18 +1,28 @@
-use commit_crafter::{config, llm};
+mod common;
+
+use commit_crafter::config::ConfigPaths;
+use commit_crafter::llm::openai::OpenAiProvider;
+use commit_crafter::llm::LlmProvider;
+use commit_crafter::{config, llm, TokenUsage};
+use common::{MockResponse, MockServer};
+use serde_json::Value;
 use std::env;
 use tempfile::tempdir;
 
+const CHAT_COMPLETION: &str = r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"feat: add azure support"}}],"usage":{"prompt_tokens":42,"completion_tokens":6,"total_tokens":48}}"#;
+
 #[test]
 fn test_openai_request_by_no_config() {
     if env::var("GITHUB_ACTIONS").is_ok() {
         eprintln!("Skipping test in GitHub Actions environment");
         return;
     }
+    common::clear_credential_env();
     let temp_dir = tempdir().unwrap();
     let prompt_dir = temp_dir.path().join("prompt.toml");
     let file_path = temp_dir.path();
     // generate prompt.toml
-    config::move_prompt_toml(&prompt_dir);
+    config::move_prompt_toml(&prompt_dir).unwrap();
     let config_str = config::generate_config_toml();
     if let Err(e) = config::write_config_to_toml(&config_str, &file_path.join("config.toml")) {
         eprintln!("Failed to write config.toml: {}", e);
@@ -20,8 +30,11 @@ fn test_openai_request_by_no_config() {
 
     // run openai_request with empty commit history
     let commit_history = vec![];
-    let result =
-        llm::openai::openai_request("diff_content", &commit_history, file_path.to_str().unwrap());
+    let result = llm::openai::openai_request(
+        "diff_content",
+        &commit_history,
+        &ConfigPaths::new(file_path),
+    );
 
     match result {
         Ok(_) => panic!("Expected an error of 'NotFound', got {:?}", result),
@@ -33,6 +46,7 @@ fn test_openai_request_by_no_config() {
 }
 
 #[test]
+#[allow(clippy::assertions_on_constants)]
 fn test_openai_request() {
     if env::var("GITHUB_ACTIONS").is_ok() {
         eprintln!("Skipping test in GitHub Actions environment");
@@ -42,7 +56,7 @@ fn test_openai_request() {
     let config_dir = format!("{}/.config/commit_crafter", home_dir);
     let prompt_dir = format!("{}/.config/commit_crafter/prompt.toml", home_dir);
     // generate prompt.toml
-    config::move_prompt_toml(&prompt_dir);
+    config::move_prompt_toml(&prompt_dir).unwrap();
 
     // run openai_request with sample commit history
     let commit_history = vec![
@@ -50,7 +64,11 @@ fn test_openai_request() {
         "fix: resolve bug in authentication".to_string(),
         "docs: update README".to_string(),
     ];
-    let result = llm::openai::openai_request("diff_content", &commit_history, &config_dir);
+    let result = llm::openai::openai_request(
+        "diff_content",
+        &commit_history,
+        &ConfigPaths::new(&config_dir),
+    );
 
     match result {
         Ok(_) => assert!(true),
@@ -59,6 +77,7 @@ fn test_openai_request() {
 }
 
 #[test]
+#[allow(clippy::assertions_on_constants)]
 fn test_openai_request_with_empty_history() {
     if env::var("GITHUB_ACTIONS").is_ok() {
         eprintln!("Skipping test in GitHub Actions environment");
@@ -68,14 +87,153 @@ fn test_openai_request_with_empty_history() {
     let config_dir = format!("{}/.config/commit_crafter", home_dir);
     let prompt_dir = format!("{}/.config/commit_crafter/prompt.toml", home_dir);
     // generate prompt.toml
-    config::move_prompt_toml(&prompt_dir);
+    config::move_prompt_toml(&prompt_dir).unwrap();
 
     // run openai_request with empty commit history
     let commit_history = vec![];
-    let result = llm::openai::openai_request("diff_content", &commit_history, &config_dir);
+    let result = llm::openai::openai_request(
+        "diff_content",
+        &commit_history,
+        &ConfigPaths::new(&config_dir),
+    );
 
     match result {
         Ok(_) => assert!(true),
         Err(e) => panic!("Expected Ok, got {:?}", e),
     }
 }
+
+#[test]
+fn test_openai_request_headers() {
+    let server = MockServer::start(vec![MockResponse::json(200, CHAT_COMPLETION)]);
+    let provider = OpenAiProvider::new("test_key", &server.url, "gpt-4o-mini");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let result = provider.generate(&messages).unwrap();
+    assert_eq!(result.text, "feat: add azure support");
+    assert_eq!(
+        result.usage,
+        Some(TokenUsage {
+            input_tokens: 42,
+            output_tokens: 6
+        })
+    );
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].request_line,
+        "POST /v1/chat/completions HTTP/1.1"
+    );
+    assert_eq!(requests[0].header("authorization"), Some("Bearer test_key"));
+    let body: Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["model"], "gpt-4o-mini");
+}
+
+#[test]
+fn test_azure_request() {
+    let server = MockServer::start(vec![MockResponse::json(200, CHAT_COMPLETION)]);
+    let provider = OpenAiProvider::azure("azure_key", &server.url, "commit-gpt", "2024-06-01");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    assert_eq!(provider.name(), "azure");
+    assert_eq!(provider.model(), "commit-gpt");
+    assert_eq!(
+        provider.generate(&messages).unwrap().text,
+        "feat: add azure support"
+    );
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].request_line,
+        "POST /openai/deployments/commit-gpt/chat/completions?api-version=2024-06-01 HTTP/1.1"
+    );
+    assert_eq!(requests[0].header("api-key"), Some("azure_key"));
+    assert_eq!(requests[0].header("authorization"), None);
+}
+
+#[test]
+fn test_azure_from_config() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    config::set_config_key("provider", "azure", &config_path).unwrap();
+    // the OpenAI settings are not used for Azure
+    config::set_config_key("openai_api_key", "openai_key", &config_path).unwrap();
+    let config = ConfigPaths::new(temp_dir.path());
+    match llm::provider_from_config(&config) {
+        Ok(_) => panic!("Expected an error for the missing Azure key"),
+        Err(e) => assert_eq!(e.to_string(), "Azure API key or endpoint is empty"),
+    }
+
+    config::set_config_key("azure_api_key", "azure_key", &config_path).unwrap();
+    config::set_config_key(
+        "azure_endpoint",
+        "https://example.openai.azure.com",
+        &config_path,
+    )
+    .unwrap();
+
+    match llm::provider_from_config(&config) {
+        Ok(_) => panic!("Expected an error for the empty deployment"),
+        Err(e) => assert_eq!(e.to_string(), "Azure deployment or API version is empty"),
+    }
+
+    config::set_config_key("azure_deployment", "commit-gpt", &config_path).unwrap();
+    let provider = llm::provider_from_config(&config).unwrap();
+    assert_eq!(provider.name(), "azure");
+    assert_eq!(provider.model(), "commit-gpt");
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_openai_stream() {
+    let server = MockServer::start(vec![MockResponse {
+        status: 200,
+        headers: vec![("Content-Type".to_string(), "text/event-stream".to_string())],
+        body: concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"feat: \"}}]}\n\n",
+            ": keep-alive\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"stream tokens\"}}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":42,\"completion_tokens\":3}}\n\n",
+            "data: [DONE]\n\n"
+        )
+        .to_string(),
+    }]);
+    let provider = OpenAiProvider::new("test_key", &server.url, "gpt-4o-mini");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let mut tokens = Vec::new();
+    let result = provider
+        .generate_stream(&messages, &mut |token| tokens.push(token.to_string()))
+        .unwrap();
+
+    assert_eq!(result.text, "feat: stream tokens");
+    assert_eq!(tokens, vec!["feat: ", "stream tokens"]);
+    assert_eq!(result.usage.map(|usage| usage.total()), Some(45));
+
+    let body: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["stream_options"]["include_usage"], true);
+}
+
+#[test]
+fn test_openai_request_body() {
+    let provider = OpenAiProvider::new("test_key", "http://localhost", "gpt-4o-mini");
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let body = provider.request_body(&messages, false).unwrap();
+    assert_eq!(body["model"], "gpt-4o-mini");
+    assert_eq!(body["messages"][0]["content"], "base prompt");
+    assert!(body.get("stream").is_none());
+
+    let body = provider.request_body(&messages, true).unwrap();
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["stream_options"]["include_usage"], true);
+
+    let azure = OpenAiProvider::azure("test_key", "http://localhost", "deployment", "2024-06-01");
+    let body = azure.request_body(&messages, true).unwrap();
+    assert_eq!(body["stream"], true);
+    assert!(body.get("stream_options").is_none());
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use commit_crafter::config::{self, ConfigPaths};
+use commit_crafter::llm::http::HttpClient;
+use commit_crafter::llm::openai::OpenAiProvider;
+use commit_crafter::llm::retry::{self, RetryPolicy};
+use commit_crafter::llm::{self, LlmProvider};
+use commit_crafter::Error;
+use common::{MockResponse, MockServer};
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+const CHAT_COMPLETION: &str = r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"fix: retry transient errors"}}]}"#;
+
+fn fast_policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(50),
+    }
+}
+
+fn provider(server: &MockServer, policy: RetryPolicy) -> OpenAiProvider {
+    OpenAiProvider::new("test_key", &server.url, "gpt-4o-mini")
+        .with_http_client(HttpClient::new(policy).unwrap())
+}
+
+#[test]
+fn test_retry_until_success() {
+    let server = MockServer::start(vec![
+        MockResponse::json(503, r#"{"error":{"message":"overloaded"}}"#),
+        MockResponse::json(429, r#"{"error":{"message":"rate limited"}}"#),
+        MockResponse::json(200, CHAT_COMPLETION),
+    ]);
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let result = provider(&server, fast_policy(3)).generate(&messages);
+    assert_eq!(result.unwrap().text, "fix: retry transient errors");
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn test_retry_gives_up_after_max_attempts() {
+    let server = MockServer::start(vec![
+        MockResponse::json(500, "{}"),
+        MockResponse::json(502, "{}"),
+        MockResponse::json(200, CHAT_COMPLETION),
+    ]);
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    match provider(&server, fast_policy(2)).generate(&messages) {
+        Err(Error::ProviderStatus { status, .. }) => assert_eq!(status, 502),
+        other => panic!("Expected a status error, got {:?}", other),
+    }
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn test_no_retry_on_client_error() {
+    let server = MockServer::start(vec![
+        MockResponse::json(401, r#"{"error":{"message":"invalid api key"}}"#),
+        MockResponse::json(200, CHAT_COMPLETION),
+    ]);
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    match provider(&server, fast_policy(3)).generate(&messages) {
+        Err(Error::ProviderStatus { status, .. }) => assert_eq!(status, 401),
+        other => panic!("Expected a status error, got {:?}", other),
+    }
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_retry_honors_retry_after() {
+    let server = MockServer::start(vec![
+        MockResponse::json(429, "{}").with_header("Retry-After", "1"),
+        MockResponse::json(200, CHAT_COMPLETION),
+    ]);
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+    let policy = RetryPolicy {
+        max_delay: Duration::from_secs(5),
+        ..fast_policy(2)
+    };
+
+    let started = Instant::now();
+    let result = provider(&server, policy).generate(&messages);
+    assert_eq!(result.unwrap().text, "fix: retry transient errors");
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[test]
+fn test_retry_on_connection_refused() {
+    // bind and drop a listener so the port is known to be closed
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let provider = OpenAiProvider::new("test_key", &format!("http://127.0.0.1:{}", port), "m")
+        .with_http_client(HttpClient::new(fast_policy(2)).unwrap());
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    assert!(matches!(provider.generate(&messages), Err(Error::Http(_))));
+}
+
+#[test]
+fn test_retry_delay() {
+    let policy = RetryPolicy {
+        max_attempts: 5,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(350),
+    };
+
+    assert_eq!(policy.delay(1, None), Duration::from_millis(100));
+    assert_eq!(policy.delay(2, None), Duration::from_millis(200));
+    assert_eq!(policy.delay(3, None), Duration::from_millis(350));
+    assert_eq!(
+        policy.delay(1, Some(Duration::from_millis(250))),
+        Duration::from_millis(250)
+    );
+    assert_eq!(
+        policy.delay(1, Some(Duration::from_secs(60))),
+        Duration::from_millis(350)
+    );
+}
+
+#[test]
+fn test_parse_retry_after() {
+    assert_eq!(retry::parse_retry_after("2"), Some(Duration::from_secs(2)));
+    assert_eq!(
+        retry::parse_retry_after(" 10 "),
+        Some(Duration::from_secs(10))
+    );
+    assert_eq!(
+        retry::parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+        None
+    );
+}
+
+#[test]
+fn test_retryable_status() {
+    for status in [408, 429, 500, 502, 503, 504] {
+        assert!(retry::is_retryable_status(status));
+    }
+    for status in [400, 401, 403, 404, 422] {
+        assert!(!retry::is_retryable_status(status));
+    }
+}
+
+#[test]
+fn test_retry_policy_from_config() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    let config = ConfigPaths::new(temp_dir.path());
+
+    assert_eq!(
+        RetryPolicy::from_config(&config).unwrap(),
+        RetryPolicy::default()
+    );
+
+    config::set_config_key("retry_max_attempts", "5", &config_path).unwrap();
+    config::set_config_key("retry_backoff_ms", "100", &config_path).unwrap();
+    let policy = RetryPolicy::from_config(&config).unwrap();
+    assert_eq!(policy.max_attempts, 5);
+    assert_eq!(policy.base_delay, Duration::from_millis(100));
+
+    assert!(config::set_config_key("retry_max_attempts", "many", &config_path).is_err());
+
+    temp_dir.close().unwrap();
+}
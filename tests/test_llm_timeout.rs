@@ -0,0 +1,65 @@
+use commit_crafter::config::{self, ConfigPaths};
+use commit_crafter::llm::http::{HttpClient, Timeouts};
+use commit_crafter::llm::openai::OpenAiProvider;
+use commit_crafter::llm::retry::RetryPolicy;
+use commit_crafter::llm::{self, LlmProvider};
+use commit_crafter::Error;
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+// accepts connections but never answers, like a black-holed proxy
+fn stalled_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let mut connections = Vec::new();
+        for stream in listener.incoming() {
+            connections.push(stream);
+        }
+    });
+    url
+}
+
+#[test]
+fn test_request_timeout() {
+    let url = stalled_server();
+    let timeouts = Timeouts {
+        request: Duration::from_millis(300),
+        connect: Duration::from_secs(1),
+    };
+    let retry = RetryPolicy {
+        max_attempts: 1,
+        ..RetryPolicy::default()
+    };
+    let provider = OpenAiProvider::new("test_key", &url, "gpt-4o-mini")
+        .with_http_client(HttpClient::with_timeouts(retry, timeouts).unwrap());
+    let messages = llm::build_messages("base prompt", "diff_content", &[]);
+
+    let started = Instant::now();
+    let result = provider.generate(&messages);
+    assert!(matches!(result, Err(Error::Timeout(_))));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_timeouts_from_config() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    config::ensure_config_initialized(temp_dir.path()).unwrap();
+    let config = ConfigPaths::new(temp_dir.path());
+
+    assert_eq!(Timeouts::from_config(&config).unwrap(), Timeouts::default());
+
+    config::set_config_key("request_timeout_secs", "15", &config_path).unwrap();
+    config::set_config_key("connect_timeout_secs", "3", &config_path).unwrap();
+    let timeouts = Timeouts::from_config(&config).unwrap();
+    assert_eq!(timeouts.request, Duration::from_secs(15));
+    assert_eq!(timeouts.connect, Duration::from_secs(3));
+
+    assert!(config::set_config_key("request_timeout_secs", "0", &config_path).is_err());
+    assert!(config::set_config_key("connect_timeout_secs", "soon", &config_path).is_err());
+
+    temp_dir.close().unwrap();
+}
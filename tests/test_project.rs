@@ -0,0 +1,122 @@
+use commit_crafter::project;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::tempdir;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn repo_with_commit(dir: &Path, message: &str) -> PathBuf {
+    fs::create_dir_all(dir).unwrap();
+    git(dir, &["init", "-q"]);
+    git(dir, &["commit", "-q", "--allow-empty", "-m", message]);
+    dir.to_path_buf()
+}
+
+#[test]
+fn test_project_id_survives_clone() {
+    let temp_dir = tempdir().unwrap();
+    let repo = repo_with_commit(&temp_dir.path().join("repo"), "init");
+    git(temp_dir.path(), &["clone", "-q", "repo", "clone"]);
+    let other = repo_with_commit(&temp_dir.path().join("other"), "another project");
+
+    let id = project::project_id(&repo);
+    assert_eq!(id.len(), 16);
+    assert_eq!(id, project::project_id(&temp_dir.path().join("clone")));
+    assert_ne!(id, project::project_id(&other));
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_project_config_dir_moves_legacy_dir() {
+    let temp_dir = tempdir().unwrap();
+    let base = temp_dir.path().join("commit_crafter");
+    let repo = repo_with_commit(&temp_dir.path().join("demo"), "init");
+
+    // where versions hashing the path with DefaultHasher kept the config
+    let mut hasher = DefaultHasher::new();
+    repo.hash(&mut hasher);
+    let legacy_dir = base
+        .join("projects")
+        .join(format!("demo-{:x}", hasher.finish()));
+    fs::create_dir_all(&legacy_dir).unwrap();
+    fs::write(legacy_dir.join("config.toml"), "stream = \"false\"\n").unwrap();
+
+    let config_dir = project::project_config_dir(&base, &repo);
+    assert_eq!(
+        config_dir,
+        base.join("projects")
+            .join(format!("demo-{}", project::project_id(&repo)))
+    );
+    assert!(!legacy_dir.exists());
+    assert_eq!(
+        fs::read_to_string(config_dir.join("config.toml")).unwrap(),
+        "stream = \"false\"\n"
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_renamed_checkout_keeps_config_dir() {
+    let temp_dir = tempdir().unwrap();
+    let base = temp_dir.path().join("commit_crafter");
+    let repo = repo_with_commit(&temp_dir.path().join("renamed"), "init");
+
+    let existing = base
+        .join("projects")
+        .join(format!("original-{}", project::project_id(&repo)));
+    fs::create_dir_all(&existing).unwrap();
+
+    assert_eq!(project::project_config_dir(&base, &repo), existing);
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_shallow_clone_uses_origin() {
+    let temp_dir = tempdir().unwrap();
+    let repo = repo_with_commit(&temp_dir.path().join("repo"), "init");
+    git(&repo, &["commit", "-q", "--allow-empty", "-m", "second"]);
+    let origin = format!("file://{}", repo.display());
+    git(
+        temp_dir.path(),
+        &["clone", "-q", "--depth", "1", &origin, "shallow"],
+    );
+    git(&repo, &["commit", "-q", "--allow-empty", "-m", "third"]);
+    git(
+        temp_dir.path(),
+        &["clone", "-q", "--depth", "1", &origin, "later"],
+    );
+
+    // the oldest commit each clone holds differs, the origin they came from does not
+    let shallow = temp_dir.path().join("shallow");
+    let id = project::project_id(&shallow);
+    assert_eq!(id, project::project_id(&temp_dir.path().join("later")));
+    assert_ne!(id, project::project_id(&repo));
+
+    // once deepened the root commit is known and the config follows it
+    let base = temp_dir.path().join("commit_crafter");
+    let shallow_dir = project::project_config_dir(&base, &shallow);
+    fs::create_dir_all(&shallow_dir).unwrap();
+    git(&shallow, &["fetch", "-q", "--unshallow"]);
+    let config_dir = project::project_config_dir(&base, &shallow);
+    assert!(config_dir
+        .to_string_lossy()
+        .ends_with(&project::project_id(&repo)));
+    assert!(config_dir.is_dir());
+    assert!(!shallow_dir.exists());
+
+    temp_dir.close().unwrap();
+}